@@ -0,0 +1,242 @@
+//! Module responsible for finding and reading log files inside zip and tar archives.
+//!
+//! An entry inside an archive is given the path of the archive, followed by `!/` and the path of
+//! the entry inside the archive, like `artifacts/job.zip!/logs/build.log`. That way, it can be
+//! matched against globs and searched for warnings just like any log file on the file system,
+//! except that it is always read whole.
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek};
+use std::path::{Path, PathBuf};
+
+use crate::compression;
+
+/// Separates the path of an archive from the path of an entry inside of it.
+pub(crate) const ENTRY_SEPARATOR: &str = "!/";
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// The archive formats we can find log files in. Tar archives may be compressed like any log file.
+enum ArchiveFormat {
+    Zip,
+    Tar,
+}
+
+impl ArchiveFormat {
+    fn of(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_lowercase();
+        if name.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else if [".tar", ".tar.gz", ".tgz", ".tar.zst", ".tar.xz", ".txz"]
+            .iter()
+            .any(|extension| name.ends_with(extension))
+        {
+            Some(ArchiveFormat::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+/// Is the file at `path` an archive we can find log files in, judging by its name?
+pub(crate) fn is_archive(path: &Path) -> bool {
+    ArchiveFormat::of(path).is_some()
+}
+
+/// The path of the `entry` inside the `archive`.
+pub(crate) fn entry_path(archive: &Path, entry: &str) -> PathBuf {
+    let mut path = OsString::from(archive);
+    path.push(ENTRY_SEPARATOR);
+    path.push(entry);
+    PathBuf::from(path)
+}
+
+/// Split the `path` of an entry into the path of its archive, and the path of the entry inside of
+/// it. Returns None if the path is not inside an archive.
+pub(crate) fn split_entry_path(path: &Path) -> Option<(&Path, &str)> {
+    let path_str = path.to_str()?;
+    path_str
+        .match_indices(ENTRY_SEPARATOR)
+        .map(|(i, separator)| (Path::new(&path_str[..i]), &path_str[i + separator.len()..]))
+        .find(|(archive, _entry)| is_archive(archive))
+}
+
+/// List the paths of the files inside the `archive`, along with their sizes once extracted.
+pub(crate) fn list_entries(archive: &Path) -> io::Result<Vec<(String, u64)>> {
+    match ArchiveFormat::of(archive) {
+        Some(format) => list_entries_in(format, File::open(archive)?),
+        None => Err(not_an_archive(archive)),
+    }
+}
+
+/// Read the files at the paths `entries` inside the `archive`, in a single pass over it, and call
+/// `found` with the path and contents of each of them. Entries not in the archive are skipped.
+pub(crate) fn read_entries<F>(archive: &Path, entries: &HashSet<&str>, found: F) -> io::Result<()>
+where
+    F: FnMut(&str, Vec<u8>),
+{
+    match ArchiveFormat::of(archive) {
+        Some(format) => read_entries_in(format, File::open(archive)?, entries, found),
+        None => Err(not_an_archive(archive)),
+    }
+}
+
+fn list_entries_in<R>(format: ArchiveFormat, archive: R) -> io::Result<Vec<(String, u64)>>
+where
+    R: Read + Seek + Send + 'static,
+{
+    let mut result = Vec::new();
+    match format {
+        ArchiveFormat::Zip => {
+            let mut zip = zip::ZipArchive::new(archive).map_err(invalid_data)?;
+            for i in 0..zip.len() {
+                let file = zip.by_index_raw(i).map_err(invalid_data)?;
+                // Directories are listed as well
+                if !file.is_dir() {
+                    result.push((file.name().map_err(invalid_data)?.into_owned(), file.size()));
+                }
+            }
+        }
+        ArchiveFormat::Tar => {
+            let archive = compression::decompressing_reader(BufReader::new(archive))?;
+            for entry in tar::Archive::new(archive).entries()? {
+                let entry = entry?;
+                if entry.header().entry_type().is_file() {
+                    result.push((tar_entry_name(&entry)?, entry.size()));
+                }
+            }
+        }
+    }
+    Ok(result)
+}
+
+fn read_entries_in<R, F>(
+    format: ArchiveFormat,
+    archive: R,
+    entries: &HashSet<&str>,
+    mut found: F,
+) -> io::Result<()>
+where
+    R: Read + Seek + Send + 'static,
+    F: FnMut(&str, Vec<u8>),
+{
+    match format {
+        ArchiveFormat::Zip => {
+            // The entries are looked up in the directory at the end of the zip
+            let mut zip = zip::ZipArchive::new(archive).map_err(invalid_data)?;
+            for entry in entries {
+                let mut contents = Vec::new();
+                match zip.by_name(entry) {
+                    Ok(mut file) => file.read_to_end(&mut contents)?,
+                    Err(zip::result::ZipError::FileNotFound) => continue,
+                    Err(e) => return Err(invalid_data(e)),
+                };
+                found(entry, contents);
+            }
+        }
+        ArchiveFormat::Tar => {
+            let archive = compression::decompressing_reader(BufReader::new(archive))?;
+            for tar_entry in tar::Archive::new(archive).entries()? {
+                let mut tar_entry = tar_entry?;
+                let name = tar_entry_name(&tar_entry)?;
+                if let Some(entry) = entries.get(name.as_str()) {
+                    let mut contents = Vec::new();
+                    tar_entry.read_to_end(&mut contents)?;
+                    found(entry, contents);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The name of an entry in a tar archive, which may start with `./`.
+fn tar_entry_name<R: Read>(entry: &tar::Entry<R>) -> io::Result<String> {
+    let path = entry.path()?;
+    Ok(path.to_string_lossy().trim_start_matches("./").to_owned())
+}
+
+fn not_an_archive(path: &Path) -> io::Error {
+    let msg = format!("`{}` is not an archive", path.display());
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+fn invalid_data<E: ToString>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Cursor, Write};
+
+    use super::*;
+
+    const LOG: &[u8] = b"src/a.c:1: warning: unused `x`\n";
+
+    #[test]
+    fn entries_are_listed_and_read_from_zip_and_tar() {
+        let read_all = |format: ArchiveFormat, archive: &[u8], entries: &[&str]| {
+            let entries: HashSet<&str> = entries.iter().cloned().collect();
+            let mut result = Vec::new();
+            let archive = Cursor::new(archive.to_vec());
+            read_entries_in(format, archive, &entries, |entry, contents| {
+                result.push((entry.to_owned(), contents))
+            })
+            .unwrap();
+            result.sort();
+            result
+        };
+        let found = |entries: &[&str]| -> Vec<(String, Vec<u8>)> {
+            entries.iter().map(|e| ((*e).to_owned(), LOG.to_vec())).collect()
+        };
+
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default();
+        zip.add_directory("logs/", options).unwrap();
+        for name in &["logs/build.log", "logs/test.log"] {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(LOG).unwrap();
+        }
+        zip.start_file("logs/empty.log", options).unwrap();
+        let zip = zip.finish().unwrap().into_inner();
+        let entries = list_entries_in(ArchiveFormat::Zip, Cursor::new(zip.clone())).unwrap();
+        let size = LOG.len() as u64;
+        let expected = vec![
+            ("logs/build.log".to_owned(), size),
+            ("logs/test.log".to_owned(), size),
+            ("logs/empty.log".to_owned(), 0),
+        ];
+        assert_eq!(entries, expected);
+        let wanted = ["logs/build.log", "logs/test.log", "logs/other.log"];
+        assert_eq!(
+            read_all(ArchiveFormat::Zip, &zip, &wanted),
+            found(&["logs/build.log", "logs/test.log"])
+        );
+
+        let mut tar = tar::Builder::new(Vec::new());
+        for name in &["./logs/build.log", "./logs/test.log"] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(size);
+            header.set_cksum();
+            tar.append_data(&mut header, name, LOG).unwrap();
+        }
+        let tar = tar.into_inner().unwrap();
+        let entries = list_entries_in(ArchiveFormat::Tar, Cursor::new(tar.clone())).unwrap();
+        assert_eq!(entries, expected[..2].to_vec());
+        assert_eq!(
+            read_all(ArchiveFormat::Tar, &tar, &wanted),
+            found(&["logs/build.log", "logs/test.log"])
+        );
+    }
+
+    #[test]
+    fn entry_paths_are_split_at_the_archive() {
+        let path = entry_path(Path::new("/ci/job!1/artifacts.tar.gz"), "logs/build.log");
+        assert_eq!(path, PathBuf::from("/ci/job!1/artifacts.tar.gz!/logs/build.log"));
+        assert_eq!(
+            split_entry_path(&path),
+            Some((Path::new("/ci/job!1/artifacts.tar.gz"), "logs/build.log"))
+        );
+        assert_eq!(split_entry_path(Path::new("/ci/job!/build.log")), None);
+    }
+}
@@ -0,0 +1,227 @@
+//! Module responsible for decompressing log files, so compressed logs can be searched as is.
+use std::io::{self, BufRead, BufReader, Cursor, Read, Write};
+
+use flate2::read::MultiGzDecoder;
+use ruzstd::decoding::{FrameDecoder, StreamingDecoder};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// The compression formats we can decompress log files from. They are told apart by the magic
+/// bytes at the start of the file, so the file name does not matter.
+enum Compression {
+    Gzip,
+    Zstd,
+    Xz,
+}
+
+impl Compression {
+    fn of(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0x1F, 0x8B, ..] => Some(Compression::Gzip),
+            [0x28, 0xB5, 0x2F, 0xFD, ..] => Some(Compression::Zstd),
+            [0xFD, b'7', b'z', b'X', b'Z', 0x00, ..] => Some(Compression::Xz),
+            _ => None,
+        }
+    }
+}
+
+/// Decompress the `bytes` read from a log file, if they are compressed. Otherwise return them as
+/// they are.
+pub(crate) fn decompress(bytes: Vec<u8>) -> io::Result<Vec<u8>> {
+    let compression = match Compression::of(&bytes) {
+        Some(compression) => compression,
+        None => return Ok(bytes),
+    };
+    let mut result = Vec::new();
+    match compression {
+        Compression::Gzip => {
+            MultiGzDecoder::new(bytes.as_slice()).read_to_end(&mut result)?;
+        }
+        Compression::Zstd => {
+            // A zstd file may consist of several frames
+            let mut input = bytes.as_slice();
+            while !input.is_empty() {
+                let mut decoder = StreamingDecoder::new(&mut input).map_err(invalid_data)?;
+                decoder.read_to_end(&mut result)?;
+            }
+        }
+        Compression::Xz => {
+            lzma_rs::xz_decompress(&mut bytes.as_slice(), &mut result).map_err(invalid_data)?;
+        }
+    }
+    Ok(result)
+}
+
+/// Are the log files starting with the bytes at the `start` decompressed while reading them? Such
+/// log files may be a lot larger once decompressed than they are on disk.
+pub(crate) fn is_decompressed_while_reading(start: &[u8]) -> bool {
+    match Compression::of(start) {
+        Some(Compression::Gzip) | Some(Compression::Zstd) => true,
+        Some(Compression::Xz) | None => false,
+    }
+}
+
+/// Wrap the `reader` of a log file in a reader decompressing it, if it is compressed, so that it
+/// need not be decompressed all at once. Xz is the exception, and is decompressed whole.
+pub(crate) fn decompressing_reader<R>(mut reader: R) -> io::Result<Box<dyn BufRead + Send>>
+where
+    R: BufRead + Send + 'static,
+{
+    let result: Box<dyn BufRead + Send> = match Compression::of(reader.fill_buf()?) {
+        None => Box::new(reader),
+        Some(Compression::Gzip) => Box::new(BufReader::new(MultiGzDecoder::new(reader))),
+        Some(Compression::Zstd) => Box::new(BufReader::new(ZstdFrames::new(reader)?)),
+        Some(Compression::Xz) => {
+            let mut result = Vec::new();
+            lzma_rs::xz_decompress(&mut reader, &mut result).map_err(invalid_data)?;
+            Box::new(Cursor::new(result))
+        }
+    };
+    Ok(result)
+}
+
+/// Whether the log file read by the `reader` is empty once decompressed. Only as much of it is
+/// decompressed as needed to tell.
+pub(crate) fn is_empty<R>(mut reader: R) -> io::Result<bool>
+where
+    R: BufRead + Send + 'static,
+{
+    if Compression::of(reader.fill_buf()?) != Some(Compression::Xz) {
+        return Ok(decompressing_reader(reader)?.fill_buf()?.is_empty());
+    }
+    // Xz can only be decompressed whole, so stop it at the first decompressed byte instead
+    let mut sink = NonEmpty(false);
+    match lzma_rs::xz_decompress(&mut reader, &mut sink) {
+        Err(_) if sink.0 => Ok(false),
+        Err(e) => Err(invalid_data(e)),
+        Ok(()) => Ok(!sink.0),
+    }
+}
+
+/// Fails any write of something, remembering that it was written.
+struct NonEmpty(bool);
+
+impl Write for NonEmpty {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.0 = true;
+        Err(io::Error::other("Not empty"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads the decompressed contents of every zstd frame in a stream, one frame after the other.
+struct ZstdFrames<R: BufRead> {
+    decoder: Option<StreamingDecoder<R, FrameDecoder>>,
+}
+
+impl<R: BufRead> ZstdFrames<R> {
+    fn new(reader: R) -> io::Result<Self> {
+        let decoder = StreamingDecoder::new(reader).map_err(invalid_data)?;
+        Ok(ZstdFrames {
+            decoder: Some(decoder),
+        })
+    }
+}
+
+impl<R: BufRead> Read for ZstdFrames<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while let Some(decoder) = &mut self.decoder {
+            let read = decoder.read(buf)?;
+            if read > 0 || buf.is_empty() {
+                return Ok(read);
+            }
+            // The frame is done, but another one may follow it
+            let mut reader = self.decoder.take().expect("Decoder is present").into_inner();
+            if !reader.fill_buf()?.is_empty() {
+                self.decoder = Some(StreamingDecoder::new(reader).map_err(invalid_data)?);
+            }
+        }
+        Ok(0)
+    }
+}
+
+fn invalid_data<E: ToString>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use flate2::write::GzEncoder;
+
+    use super::*;
+
+    #[test]
+    fn compressed_logs_are_decompressed_by_magic_bytes() {
+        let log = b"src/a.c:1: warning: unused `x`\n".to_vec();
+        assert_eq!(decompress(log.clone()).unwrap(), log);
+
+        let mut gzip = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(&log).unwrap();
+        assert_eq!(decompress(gzip.finish().unwrap()).unwrap(), log);
+
+        let mut xz = Vec::new();
+        lzma_rs::xz_compress(&mut log.as_slice(), &mut xz).unwrap();
+        assert_eq!(decompress(xz).unwrap(), log);
+
+        let zstd = ruzstd::encoding::compress_to_vec(
+            log.as_slice(),
+            ruzstd::encoding::CompressionLevel::Fastest,
+        );
+        assert_eq!(decompress(zstd).unwrap(), log);
+    }
+
+    #[test]
+    fn compressed_logs_are_decompressed_while_reading() {
+        let log = b"src/a.c:1: warning: unused `x`\n".to_vec();
+        let read_all = |bytes: Vec<u8>| {
+            let mut result = Vec::new();
+            let mut reader = decompressing_reader(Cursor::new(bytes)).unwrap();
+            reader.read_to_end(&mut result).unwrap();
+            result
+        };
+        assert_eq!(read_all(log.clone()), log);
+
+        let mut gzip = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(&log).unwrap();
+        assert_eq!(read_all(gzip.finish().unwrap()), log);
+
+        // Two zstd frames, as if compressed logs were concatenated
+        let mut zstd = Vec::new();
+        for _ in 0..2 {
+            zstd.extend(ruzstd::encoding::compress_to_vec(
+                log.as_slice(),
+                ruzstd::encoding::CompressionLevel::Fastest,
+            ));
+        }
+        assert_eq!(read_all(zstd), [log.as_slice(), log.as_slice()].concat());
+    }
+
+    #[test]
+    fn compressed_logs_are_empty_when_decompressed_empty() {
+        let is_empty_compressed = |log: &[u8]| {
+            let mut gzip = GzEncoder::new(Vec::new(), flate2::Compression::default());
+            gzip.write_all(log).unwrap();
+            let gzip = gzip.finish().unwrap();
+            // Compressing an empty log still gives some bytes
+            assert!(!gzip.is_empty());
+
+            let mut xz = Vec::new();
+            lzma_rs::xz_compress(&mut &log[..], &mut xz).unwrap();
+            let zstd = ruzstd::encoding::compress_to_vec(
+                log,
+                ruzstd::encoding::CompressionLevel::Fastest,
+            );
+            vec![log.to_vec(), gzip, xz, zstd]
+                .into_iter()
+                .map(|bytes| is_empty(Cursor::new(bytes)).unwrap())
+                .collect::<Vec<bool>>()
+        };
+        assert_eq!(is_empty_compressed(b""), vec![true; 4]);
+        assert_eq!(is_empty_compressed(b"src/a.c:1: warning: unused `x`\n"), vec![false; 4]);
+    }
+}
@@ -1,5 +1,5 @@
 //! Module responsible for structures and functionality related to Limits and Limit files.
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fmt::Display;
 use std::fs::read_to_string;
@@ -8,9 +8,8 @@ use std::path::{Path, PathBuf};
 use id_arena::Id;
 use linked_hash_map::LinkedHashMap;
 use serde::{Deserialize, Serialize};
-use toml;
 
-use crate::settings::Kind;
+use crate::settings::{Kind, CENTRAL_LIMITS_KEY};
 use crate::utils;
 use crate::utils::SearchableArena;
 use crate::warnings::EntryCount;
@@ -47,7 +46,7 @@ impl Category {
         }
     }
 
-    fn from_str(as_str: &str, arena: &mut SearchableArena) -> Self {
+    pub(crate) fn from_str(as_str: &str, arena: &mut SearchableArena) -> Self {
         if as_str == "_" {
             Category::none()
         } else {
@@ -56,21 +55,119 @@ impl Category {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Ord, PartialOrd, Hash, Clone)]
+/// A Severity is the level a tool reports a warning with, such as `note`, `warning` or `error`.
+/// It is only known for [Kind](../settings/struct.Kind.html)s capturing `severity`, and limits may
+/// be declared separately for each Severity. The "none" Severity matches any severity.
+pub(crate) struct Severity(Option<Id<String>>);
+
+impl Severity {
+    pub fn new(id: Id<String>) -> Self {
+        Severity(Some(id))
+    }
+
+    pub fn none() -> Self {
+        Severity(None)
+    }
+
+    pub fn is_none(&self) -> bool {
+        self.0.is_none()
+    }
+
+    pub fn remap_id(&mut self, from: &SearchableArena, to: &SearchableArena) {
+        if let Some(sev_id) = self.0 {
+            let sev_str = from
+                .lookup(sev_id)
+                .expect("String not present in new arena. Did you forget to call add_all?");
+            self.0 = to.get_id(sev_str);
+        }
+    }
+
+    pub fn to_str<'arena>(&self, arena: &'arena SearchableArena) -> Option<&'arena str> {
+        match self.0 {
+            Some(sev_id) => Some(arena.lookup(sev_id).unwrap()),
+            None => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// What a [Threshold](struct.Threshold.html) is measured in. Either the number of warnings, the
+/// number of distinct culprit files having at least one warning, or the score of the warnings,
+/// where every warning counts with its configured weight.
+pub(crate) enum Metric {
+    Warnings,
+    Files,
+    Score,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// A Threshold is a single numerical limit together with the [Metric](enum.Metric.html) it is
+/// measured in. A limit may also be "infinity", represented by None.
+/// Optionally, a Threshold also caps how many warnings any single culprit file may have.
+pub(crate) struct Threshold {
+    pub(crate) limit: Option<u64>,
+    pub(crate) metric: Metric,
+    pub(crate) max_per_file: Option<u64>,
+}
+
+impl Threshold {
+    pub fn warnings(limit: Option<u64>) -> Self {
+        Threshold {
+            limit: limit,
+            metric: Metric::Warnings,
+            max_per_file: None,
+        }
+    }
+
+    pub fn files(limit: Option<u64>) -> Self {
+        Threshold {
+            limit: limit,
+            metric: Metric::Files,
+            max_per_file: None,
+        }
+    }
+
+    pub fn score(limit: Option<u64>) -> Self {
+        Threshold {
+            limit: limit,
+            metric: Metric::Score,
+            max_per_file: None,
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.limit == Some(0)
+    }
+
+    fn lower_to(&mut self, actual: u64) {
+        if let Some(x) = self.limit.as_mut() {
+            *x = actual;
+        } // inf limit, do nothing
+    }
+}
+
 /// A LimitsFile declares a [Limit](struct.Limit.html) for a [Kind](../settings/struct.Kind.html) as a threshold
-/// of number of warnings allowed.
+/// of number of warnings, or number of files with warnings, allowed. A Kind may also have
+/// separate Limits for some [Severities](struct.Severity.html).
 #[derive(Clone, PartialEq)]
 pub(crate) struct LimitsFile {
-    inner: LinkedHashMap<Kind, Limit>,
+    inner: LinkedHashMap<(Kind, Severity), Limit>,
 }
 
 impl LimitsFile {
-    pub fn iter(&self) -> impl Iterator<Item = (&Kind, &Limit)> {
-        self.inner.iter()
+    pub fn iter(&self) -> impl Iterator<Item = (&Kind, &Severity, &Limit)> {
+        self.inner.iter().map(|((kind, severity), limit)| (kind, severity, limit))
     }
 
     #[cfg(test)]
     pub fn get_limit(&self, kind: &Kind) -> Option<&Limit> {
-        self.inner.get(kind)
+        self.get_severity_limit(kind, &Severity::none())
+    }
+
+    #[cfg(test)]
+    pub fn get_severity_limit(&self, kind: &Kind, severity: &Severity) -> Option<&Limit> {
+        self.inner.get(&(kind.clone(), severity.clone()))
     }
 
     pub(crate) fn as_serializable(&self, arena: &SearchableArena) -> impl Serialize {
@@ -81,11 +178,79 @@ impl LimitsFile {
             F(f32),
         }
 
+        impl From<Option<u64>> for IntOrFloat {
+            fn from(limit: Option<u64>) -> Self {
+                match limit {
+                    Some(x) => IntOrFloat::I(x),
+                    None => IntOrFloat::F(f32::INFINITY),
+                }
+            }
+        }
+
+        #[derive(Serialize)]
+        struct RawTable {
+            #[serde(skip_serializing_if = "Option::is_none")]
+            warnings: Option<IntOrFloat>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            files: Option<IntOrFloat>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            score: Option<IntOrFloat>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            max_per_file: Option<u64>,
+        }
+
+        #[derive(Serialize)]
+        #[serde(untagged)]
+        enum RawThreshold {
+            Warnings(IntOrFloat),
+            Table(RawTable),
+        }
+
+        impl From<&Threshold> for RawThreshold {
+            fn from(threshold: &Threshold) -> Self {
+                match (threshold.metric, threshold.max_per_file) {
+                    (Metric::Warnings, None) => RawThreshold::Warnings(threshold.limit.into()),
+                    (Metric::Warnings, Some(_)) => RawThreshold::Table(RawTable {
+                        // Leaving out the limit means infinity
+                        warnings: threshold.limit.map(IntOrFloat::I),
+                        files: None,
+                        score: None,
+                        max_per_file: threshold.max_per_file,
+                    }),
+                    (Metric::Files, _) => RawThreshold::Table(RawTable {
+                        warnings: None,
+                        files: Some(threshold.limit.into()),
+                        score: None,
+                        max_per_file: threshold.max_per_file,
+                    }),
+                    (Metric::Score, _) => RawThreshold::Table(RawTable {
+                        warnings: None,
+                        files: None,
+                        score: Some(threshold.limit.into()),
+                        max_per_file: threshold.max_per_file,
+                    }),
+                }
+            }
+        }
+
+        #[derive(Serialize)]
+        #[serde(untagged)]
+        enum RawCategoryEntry {
+            Threshold(RawThreshold),
+            PerSeverity(
+                #[serde(serialize_with = "toml::ser::tables_last")]
+                LinkedHashMap<String, RawThreshold>,
+            ),
+        }
+
         #[derive(Serialize)]
         #[serde(untagged)]
         enum RawLimitEntry {
-            Number(IntOrFloat),
-            PerCategory(LinkedHashMap<String, IntOrFloat>),
+            Number(RawThreshold),
+            PerCategory(
+                #[serde(serialize_with = "toml::ser::tables_last")]
+                LinkedHashMap<String, RawCategoryEntry>,
+            ),
         }
 
         #[derive(Serialize)]
@@ -95,24 +260,62 @@ impl LimitsFile {
             V(LinkedHashMap<String, RawLimitEntry>),
         }
 
+        fn per_category(
+            limit: &Limit,
+            arena: &SearchableArena,
+        ) -> LinkedHashMap<String, RawThreshold> {
+            match limit {
+                Limit::Number(threshold) => {
+                    let mut cat_dict = LinkedHashMap::new();
+                    cat_dict.insert("_".to_owned(), threshold.into());
+                    cat_dict
+                }
+                Limit::PerCategory(dict) => dict
+                    .iter()
+                    .map(|(cat, threshold)| {
+                        let cat_str = cat.to_str(arena).unwrap_or("_").to_owned();
+                        (cat_str, threshold.into())
+                    })
+                    .collect(),
+            }
+        }
+
+        // Kinds with limits per severity must be written as tables
+        let with_severities: HashSet<&Kind> = self
+            .inner
+            .keys()
+            .filter(|(_kind, severity)| !severity.is_none())
+            .map(|(kind, _severity)| kind)
+            .collect();
+
         let mut as_map = LinkedHashMap::new();
-        for (kind, val) in &self.inner {
+        for ((kind, severity), val) in &self.inner {
+            if !severity.is_none() {
+                continue;
+            }
             let raw_val = match val {
-                Limit::Number(Some(x)) => RawLimitEntry::Number(IntOrFloat::I(*x)),
-                Limit::Number(None) => RawLimitEntry::Number(IntOrFloat::F(std::f32::INFINITY)),
-                Limit::PerCategory(dict) => {
-                    let mut cat_dict = LinkedHashMap::new();
-                    for (cat, val) in dict {
-                        let limit = match val {
-                            Some(x) => IntOrFloat::I(*x),
-                            None => IntOrFloat::F(std::f32::INFINITY),
-                        };
-                        cat_dict.insert(cat.to_str(&arena).unwrap_or("_").to_owned(), limit);
-                    }
-                    RawLimitEntry::PerCategory(cat_dict)
+                Limit::Number(threshold) if !with_severities.contains(kind) => {
+                    RawLimitEntry::Number(threshold.into())
                 }
+                _ => RawLimitEntry::PerCategory(
+                    per_category(val, arena)
+                        .into_iter()
+                        .map(|(cat, threshold)| (cat, RawCategoryEntry::Threshold(threshold)))
+                        .collect(),
+                ),
             };
-            as_map.insert(kind.to_str(&arena).to_owned(), raw_val);
+            as_map.insert(kind.to_str(arena).to_owned(), raw_val);
+        }
+        for ((kind, severity), val) in &self.inner {
+            if let Some(sev_str) = severity.to_str(arena) {
+                let raw_val = as_map
+                    .entry(kind.to_str(arena).to_owned())
+                    .or_insert_with(|| RawLimitEntry::PerCategory(LinkedHashMap::new()));
+                if let RawLimitEntry::PerCategory(dict) = raw_val {
+                    let section = RawCategoryEntry::PerSeverity(per_category(val, arena));
+                    dict.insert(sev_str.to_owned(), section);
+                }
+            }
         }
         Inner::V(as_map)
     }
@@ -122,7 +325,7 @@ impl LimitsFile {
         arena: &'arena SearchableArena,
     ) -> impl Display + 'me {
         utils::fmt_helper(move |f| {
-            let as_string = toml::ser::to_string(&self.as_serializable(&arena)).map_err(|e| {
+            let as_string = toml::ser::to_string(&self.as_serializable(arena)).map_err(|e| {
                 eprintln!("Could not display LimitsFile: `{}`", e);
                 std::fmt::Error
             })?;
@@ -131,19 +334,15 @@ impl LimitsFile {
     }
 
     pub fn zero(&mut self, these: &HashSet<&Kind>) {
-        for (kind, limit) in self.inner.iter_mut() {
+        for ((kind, _severity), limit) in self.inner.iter_mut() {
             if !these.contains(&kind) {
                 continue;
             }
             match limit {
-                Limit::Number(Some(x)) => *x = 0,
-                Limit::Number(None) => { /* inf limit, do nothing */ }
+                Limit::Number(threshold) => threshold.lower_to(0),
                 Limit::PerCategory(per_cat) => {
-                    for (_cat, inner_limit) in per_cat.iter_mut() {
-                        match inner_limit {
-                            Some(x) => *x = 0,
-                            None => { /* inf limit, do nothing*/ }
-                        }
+                    for (_cat, threshold) in per_cat.iter_mut() {
+                        threshold.lower_to(0);
                     }
                 }
             }
@@ -153,19 +352,18 @@ impl LimitsFile {
     pub fn prune_categories(&mut self) {
         enum PruneResult<'a> {
             AllZero,
-            OnlyOne(&'a Option<u64>),
+            OnlyOne(&'a Threshold),
             StillSomeLeft,
         }
 
-        for (_kind, limit) in self.inner.iter_mut() {
+        for (_kind_and_severity, limit) in self.inner.iter_mut() {
             let prune_result = if let Limit::PerCategory(per_cat) = limit {
                 // LinkedHashMap doesn't have retain() :'(
                 *per_cat = per_cat
                     .into_iter()
-                    // Checks if the contained value is zero, Option::contains is not stable yet.
-                    .filter(|(_cat, val)| !(val.is_some() && val.unwrap() == 0))
-                    .map(|(cat, val)| (cat.clone(), *val))
-                    .collect::<LinkedHashMap<Category, Option<u64>>>();
+                    .filter(|(_cat, threshold)| !threshold.is_zero())
+                    .map(|(cat, threshold)| (cat.clone(), *threshold))
+                    .collect::<LinkedHashMap<Category, Threshold>>();
                 if per_cat.is_empty() {
                     PruneResult::AllZero
                 } else if per_cat.len() == 1 {
@@ -179,7 +377,7 @@ impl LimitsFile {
 
             match prune_result {
                 PruneResult::AllZero => {
-                    *limit = Limit::Number(Some(0));
+                    *limit = Limit::Number(Threshold::warnings(Some(0)));
                 }
                 PruneResult::OnlyOne(value) => *limit = Limit::Number(*value),
                 PruneResult::StillSomeLeft => { /* do nothing */ }
@@ -188,23 +386,19 @@ impl LimitsFile {
     }
 
     pub fn update_limits(&mut self, updated_count: &EntryCount) {
-        let limit = self
-            .inner
-            .get_mut(&updated_count.entry().kind)
-            .expect("Kind not found in LimitsFile!");
+        let entry = updated_count.entry();
+        let limit = match self.inner.get_mut(&(entry.kind.clone(), entry.severity.clone())) {
+            Some(limit) => limit,
+            // Counted towards the default limit of the kind, which is declared in Wcnt.toml
+            None => return,
+        };
         let actual = updated_count.actual;
         match limit {
-            Limit::Number(Some(x)) => *x = actual,
-            Limit::Number(None) => { /* inf limit, do nothing */ }
+            Limit::Number(threshold) => threshold.lower_to(actual),
             Limit::PerCategory(per_cat) => {
-                let inner_limit = per_cat.get_mut(&updated_count.entry().category);
-                if let Some(maybe_limit) = inner_limit {
-                    match maybe_limit {
-                        Some(x) => *x = actual,
-                        None => { /* inf limit, do nothing*/ }
-                    }
-                } else {
-                    panic!("We got a warning for a category that we don't have?");
+                // Otherwise counted towards the default limit of the kind, as for a missing kind
+                if let Some(threshold) = per_cat.get_mut(&entry.category) {
+                    threshold.lower_to(actual);
                 }
             }
         }
@@ -212,21 +406,23 @@ impl LimitsFile {
 }
 
 #[derive(Debug, PartialEq, Clone)]
-/// A Limit can either be a single number, which should hold for any [Category](struct.Category.html)
-/// of warnings for that [Kind](../settings/struct.Kind.html), or be declared per category.
-/// A limit may also be "infinity", represented by None.
+/// A Limit can either be a single [Threshold](struct.Threshold.html), which should hold for any
+/// [Category](struct.Category.html) of warnings for that [Kind](../settings/struct.Kind.html), or
+/// be declared per category.
 pub(crate) enum Limit {
-    Number(Option<u64>),
-    PerCategory(LinkedHashMap<Category, Option<u64>>),
+    Number(Threshold),
+    PerCategory(LinkedHashMap<Category, Threshold>),
 }
 
 #[derive(PartialEq, Eq, Ord, PartialOrd, Debug, Clone, Hash)]
 /// A LimitsEntry is a shorthand representation for a single numerical threshold within the system.
 /// To uniquely identify a [Limit](enum.Limit.html), you need a Path, a
-/// [Kind](../settings/struct.Kind.html) and a [Category](struct.Category.html).
+/// [Kind](../settings/struct.Kind.html), a [Severity](struct.Severity.html) and a
+/// [Category](struct.Category.html).
 pub(crate) struct LimitsEntry {
     pub(crate) limits_file: Option<PathBuf>,
     pub(crate) kind: Kind,
+    pub(crate) severity: Severity,
     pub(crate) category: Category,
 }
 
@@ -235,18 +431,34 @@ impl LimitsEntry {
         LimitsEntry {
             limits_file: limits_file.map(Into::into),
             kind: kind,
+            severity: Severity::none(),
             category: category,
         }
     }
 
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
     pub fn without_category(&self) -> Self {
         LimitsEntry {
             limits_file: self.limits_file.clone(),
             kind: self.kind.clone(),
+            severity: self.severity.clone(),
             category: Category::none(),
         }
     }
 
+    pub fn without_severity(&self) -> Self {
+        LimitsEntry {
+            limits_file: self.limits_file.clone(),
+            kind: self.kind.clone(),
+            severity: Severity::none(),
+            category: self.category.clone(),
+        }
+    }
+
     pub fn display<'me, 'arena: 'me>(
         &'me self,
         arena: &'arena SearchableArena,
@@ -273,15 +485,14 @@ impl LimitsEntry {
                     write!(f, "{}", path.display())?;
                 }
                 None => {
-                    write!(f, "_")?;
+                    write!(f, "<outside source tree>")?;
                 }
             };
-            write!(
-                f,
-                ":[{}/{}]",
-                self.kind.to_str(&arena),
-                self.category.to_str(&arena).unwrap_or("_")
-            )
+            write!(f, ":[{}", self.kind.to_str(arena))?;
+            if let Some(sev_str) = self.severity.to_str(arena) {
+                write!(f, ".{}", sev_str)?;
+            }
+            write!(f, "/{}]", self.category.to_str(arena).unwrap_or("_"))
         })
     }
 }
@@ -291,17 +502,231 @@ pub(crate) fn parse_limits_file(
     arena: &mut SearchableArena,
     file: &Path,
     categorizables: &HashSet<Kind>,
+    severitizables: &HashSet<Kind>,
 ) -> Result<LimitsFile, Box<dyn Error>> {
     let file_contents = read_to_string(file)?;
-    parse_limits_file_from_str(arena, &file_contents, &categorizables)
+    LimitsFormat::of(file)
+        .to_toml(&file_contents)
+        .and_then(|as_toml| {
+            parse_limits_file_from_str(arena, &as_toml, categorizables, severitizables)
+        })
         .map_err(|e| format!("Could not parse `{}`. Reason `{}`", file.display(), e).into())
 }
 
+/// Write the `limits` to `file`, in the format decided by its extension.
+pub(crate) fn write_limits_file(
+    file: &Path,
+    limits: &LimitsFile,
+    arena: &SearchableArena,
+) -> Result<(), Box<dyn Error>> {
+    let as_string = LimitsFormat::of(file).serialize(limits, arena)?;
+    std::fs::write(file, as_string)?;
+    Ok(())
+}
+
+/// Limits declared in one central file, in a `limits` table keyed by the directories they apply
+/// to, instead of in limits files spread out through the tree. Each directory gets a
+/// [LimitsFile](struct.LimitsFile.html) of its own, at the path a limits file in that directory
+/// would have had, so they are matched with the warnings in the same way. Directories are relative
+/// to the central file.
+pub(crate) struct CentralLimits {
+    file: PathBuf,
+    directories: LinkedHashMap<PathBuf, String>,
+}
+
+impl CentralLimits {
+    pub(crate) fn parse(
+        arena: &mut SearchableArena,
+        file: &Path,
+        limits_file_name: &str,
+        categorizables: &HashSet<Kind>,
+        severitizables: &HashSet<Kind>,
+    ) -> Result<(Self, HashMap<PathBuf, LimitsFile>), Box<dyn Error>> {
+        let file = file
+            .canonicalize()
+            .map_err(|e| format!("Could not find `{}`. Reason `{}`", file.display(), e))?;
+        let root = file.parent().expect("A file always has a parent");
+        let contents = read_to_string(&file)?;
+        let (directories, limits) = parse_central_limits_from_str(
+            arena,
+            root,
+            limits_file_name,
+            &contents,
+            categorizables,
+            severitizables,
+        )
+        .map_err(|e| format!("Could not parse `{}`. Reason `{}`", file.display(), e))?;
+        let central = CentralLimits {
+            file: file,
+            directories: directories,
+        };
+        Ok((central, limits))
+    }
+
+    /// Write the `limits` back into the central file, leaving everything outside the `limits`
+    /// table as it was.
+    pub(crate) fn write(
+        &self,
+        limits: &HashMap<PathBuf, LimitsFile>,
+        arena: &SearchableArena,
+    ) -> Result<(), Box<dyn Error>> {
+        let contents = read_to_string(&self.file)?;
+        let updated = update_central_limits_in_str(&contents, &self.directories, limits, arena)?;
+        std::fs::write(&self.file, updated)?;
+        Ok(())
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.file
+    }
+}
+
+type CentralLimitsParts = (LinkedHashMap<PathBuf, String>, HashMap<PathBuf, LimitsFile>);
+/// Parse the `limits` table of `contents`, with directories relative to `root`.
+fn parse_central_limits_from_str(
+    arena: &mut SearchableArena,
+    root: &Path,
+    limits_file_name: &str,
+    contents: &str,
+    categorizables: &HashSet<Kind>,
+    severitizables: &HashSet<Kind>,
+) -> Result<CentralLimitsParts, Box<dyn Error>> {
+    let mut directories = LinkedHashMap::new();
+    let mut limits = HashMap::new();
+    let as_value: toml::Value = toml::from_str(contents)?;
+    let table = match as_value.get(CENTRAL_LIMITS_KEY) {
+        Some(toml::Value::Table(table)) => table.clone(),
+        Some(_) => return Err("`limits` must be a table of directories.".into()),
+        None => toml::value::Table::new(),
+    };
+    for (dir, dir_limits) in table {
+        if !dir_limits.is_table() {
+            return Err(format!("Limits for directory `{}` must be a table.", dir).into());
+        }
+        let as_toml = toml::to_string(&dir_limits)?;
+        let limits_file =
+            parse_limits_file_from_str(arena, &as_toml, categorizables, severitizables)
+                .map_err(|e| format!("Limits for directory `{}`: {}", dir, e))?;
+        let path = root.join(&dir).join(limits_file_name);
+        limits.insert(path.clone(), limits_file);
+        directories.insert(path, dir);
+    }
+    Ok((directories, limits))
+}
+
+/// Put an empty line before the header of the `table`, and all tables within it.
+fn separate_tables(table: &mut toml_edit::Table) {
+    table.decor_mut().set_prefix("\n");
+    for (_key, item) in table.iter_mut() {
+        if let toml_edit::Item::Table(sub_table) = item {
+            separate_tables(sub_table);
+        }
+    }
+}
+
+/// Replace the `limits` table of `contents` with the `limits` of the `directories`.
+fn update_central_limits_in_str(
+    contents: &str,
+    directories: &LinkedHashMap<PathBuf, String>,
+    limits: &HashMap<PathBuf, LimitsFile>,
+    arena: &SearchableArena,
+) -> Result<String, Box<dyn Error>> {
+    let mut document: toml_edit::DocumentMut = contents.parse()?;
+    let mut limits_table = toml_edit::Table::new();
+    limits_table.set_implicit(true);
+    for (path, dir) in directories {
+        let limits_file = limits.get(path).expect("Every directory has limits");
+        let as_string = toml::to_string(&limits_file.as_serializable(arena))?;
+        let as_document: toml_edit::DocumentMut = as_string.parse()?;
+        let mut dir_table = as_document.as_table().clone();
+        // Only write a header for the directory if it has limits not in a table of their own
+        dir_table.set_implicit(true);
+        separate_tables(&mut dir_table);
+        limits_table.insert(dir, toml_edit::Item::Table(dir_table));
+    }
+    document.insert(CENTRAL_LIMITS_KEY, toml_edit::Item::Table(limits_table));
+    Ok(document.to_string())
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// The format of a limits file. Limits are written in TOML, unless the extension of the file says
+/// it's JSON or YAML. All formats describe the same [LimitsFile](struct.LimitsFile.html), where
+/// JSON, lacking infinity, writes `inf` as a string.
+pub(crate) enum LimitsFormat {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl LimitsFormat {
+    pub(crate) fn of(file: &Path) -> Self {
+        match file.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => LimitsFormat::Json,
+            Some("yaml") | Some("yml") => LimitsFormat::Yaml,
+            _ => LimitsFormat::Toml,
+        }
+    }
+
+    /// Translate the `contents` of a limits file in this format into TOML, so they can all be
+    /// parsed the same way.
+    fn to_toml(self, contents: &str) -> Result<String, Box<dyn Error>> {
+        fn inf_from_str(value: &mut toml::Value) {
+            match value {
+                toml::Value::String(x) if x == "inf" => {
+                    *value = toml::Value::Float(f64::INFINITY)
+                }
+                toml::Value::Table(table) => table.iter_mut().for_each(|(_k, v)| inf_from_str(v)),
+                _ => (),
+            }
+        }
+
+        let mut value: toml::Value = match self {
+            LimitsFormat::Toml => return Ok(contents.to_owned()),
+            LimitsFormat::Json => serde_json::from_str(contents)?,
+            // An empty YAML document is null
+            LimitsFormat::Yaml if contents.trim().is_empty() => {
+                toml::Value::Table(toml::value::Table::new())
+            }
+            LimitsFormat::Yaml => serde_yaml::from_str(contents)?,
+        };
+        inf_from_str(&mut value);
+        Ok(toml::to_string(&value)?)
+    }
+
+    fn serialize(
+        self,
+        limits: &LimitsFile,
+        arena: &SearchableArena,
+    ) -> Result<String, Box<dyn Error>> {
+        fn inf_to_str(value: &mut toml::Value) {
+            match value {
+                toml::Value::Float(x) if x.is_infinite() => {
+                    *value = toml::Value::String("inf".to_owned())
+                }
+                toml::Value::Table(table) => table.iter_mut().for_each(|(_k, v)| inf_to_str(v)),
+                _ => (),
+            }
+        }
+
+        let serializable = limits.as_serializable(arena);
+        match self {
+            LimitsFormat::Toml => Ok(toml::to_string(&serializable)?),
+            LimitsFormat::Json => {
+                let mut value = toml::Value::try_from(serializable)?;
+                inf_to_str(&mut value);
+                Ok(serde_json::to_string_pretty(&value)?)
+            }
+            LimitsFormat::Yaml => Ok(serde_yaml::to_string(&serializable)?),
+        }
+    }
+}
+
 /// Parse the string `cfg` in toml format into a [LimitsFile](struct.LimitsFile.html) structure.
 fn parse_limits_file_from_str(
     arena: &mut SearchableArena,
     cfg: &str,
     categorizables: &HashSet<Kind>,
+    severitizables: &HashSet<Kind>,
 ) -> Result<LimitsFile, Box<dyn Error>> {
     #[derive(Deserialize)]
     #[serde(untagged)]
@@ -325,42 +750,138 @@ fn parse_limits_file_from_str(
         }
     }
 
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct RawTable {
+        warnings: Option<IntOrFloat>,
+        files: Option<IntOrFloat>,
+        score: Option<IntOrFloat>,
+        max_per_file: Option<u64>,
+    }
+
+    impl RawTable {
+        fn is_empty(&self) -> bool {
+            self.warnings.is_none()
+                && self.files.is_none()
+                && self.score.is_none()
+                && self.max_per_file.is_none()
+        }
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawThreshold {
+        Warnings(IntOrFloat),
+        Table(RawTable),
+    }
+
+    impl RawThreshold {
+        fn to_threshold(&self) -> Result<Threshold, Box<dyn Error>> {
+            match self {
+                RawThreshold::Warnings(x) => Ok(Threshold::warnings(x.to_limit()?)),
+                RawThreshold::Table(table) => {
+                    let mut threshold = match (&table.warnings, &table.files, &table.score) {
+                        (Some(x), None, None) => Threshold::warnings(x.to_limit()?),
+                        (None, Some(x), None) => Threshold::files(x.to_limit()?),
+                        (None, None, Some(x)) => Threshold::score(x.to_limit()?),
+                        // Only `max_per_file` given, allow any total
+                        (None, None, None) => Threshold::warnings(None),
+                        _ => {
+                            return Err(
+                                "Only one of `warnings`, `files` and `score` can be limited.".into()
+                            )
+                        }
+                    };
+                    threshold.max_per_file = table.max_per_file;
+                    Ok(threshold)
+                }
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawCategoryEntry<'input> {
+        Threshold(RawThreshold),
+        #[serde(borrow)]
+        PerSeverity(LinkedHashMap<&'input str, RawThreshold>),
+    }
+
     #[derive(Deserialize)]
     #[serde(untagged)]
     enum RawLimitEntry<'input> {
-        Number(IntOrFloat),
+        Number(RawThreshold),
         #[serde(borrow)]
-        PerCategory(LinkedHashMap<&'input str, IntOrFloat>),
+        PerCategory(LinkedHashMap<&'input str, RawCategoryEntry<'input>>),
     }
 
-    let as_raw_dict: LinkedHashMap<&str, RawLimitEntry> = toml::from_str(&cfg)?;
+    let to_per_category = |arena: &mut SearchableArena,
+                           kind: &Kind,
+                           kind_str: &str,
+                           dict: Vec<(&str, RawThreshold)>|
+     -> Result<Limit, Box<dyn Error>> {
+        let mut per_category = LinkedHashMap::new();
+        for (cat_str, x) in dict {
+            if cat_str != "_" && !categorizables.contains(kind) {
+                return Err(format!("Kind `{}` is not categorizable.", kind_str).into());
+            }
+            let threshold = x.to_threshold()?;
+            let category = Category::from_str(cat_str, arena);
+            per_category.insert(category, threshold);
+        }
+        Ok(Limit::PerCategory(per_category))
+    };
+
+    let as_raw_dict: LinkedHashMap<&str, RawLimitEntry> = toml::from_str(cfg)?;
     let mut result = LinkedHashMap::new();
 
     for (key, val) in as_raw_dict.into_iter() {
-        let kind_id = arena.get_id(&key).ok_or_else(|| {
+        let kind_id = arena.get_id(key).ok_or_else(|| {
             format!(
                 "Referred to kind `{}` which has not been configured in the settings.",
                 key
             )
         })?;
         let kind = Kind::new(kind_id);
-        let converted = match val {
-            RawLimitEntry::Number(x) => Limit::Number(x.to_limit()?),
+        let val = match val {
+            // An empty table is an empty set of per category limits, not a threshold
+            RawLimitEntry::Number(RawThreshold::Table(ref table)) if table.is_empty() => {
+                RawLimitEntry::PerCategory(LinkedHashMap::new())
+            }
+            other => other,
+        };
+        match val {
+            RawLimitEntry::Number(x) => {
+                result.insert((kind, Severity::none()), Limit::Number(x.to_threshold()?));
+            }
             RawLimitEntry::PerCategory(dict) => {
-                if !categorizables.contains(&kind) {
-                    return Err(format!("Kind `{}` is not categorizable.", key).into());
-                }
-                let mut per_category = LinkedHashMap::new();
-                for (cat_str, x) in dict {
-                    let limit = x.to_limit()?;
-                    let category = Category::from_str(cat_str, arena);
-                    per_category.insert(category, limit);
+                let mut categories = Vec::new();
+                let mut severities = Vec::new();
+                for (sub_key, entry) in dict {
+                    match entry {
+                        RawCategoryEntry::Threshold(x) => categories.push((sub_key, x)),
+                        RawCategoryEntry::PerSeverity(d) => severities.push((sub_key, d)),
+                    }
                 }
 
-                Limit::PerCategory(per_category)
+                if !categories.is_empty() || severities.is_empty() {
+                    let limit = to_per_category(arena, &kind, key, categories)?;
+                    result.insert((kind.clone(), Severity::none()), limit);
+                }
+                for (sev_str, d) in severities {
+                    if !severitizables.contains(&kind) {
+                        return Err(format!(
+                            "Kind `{}` does not capture `severity`, but has limits for `{}`.",
+                            key, sev_str
+                        )
+                        .into());
+                    }
+                    let severity = Severity::new(arena.get_or_insert(sev_str));
+                    let limit = to_per_category(arena, &kind, key, d.into_iter().collect())?;
+                    result.insert((kind.clone(), severity), limit);
+                }
             }
-        };
-        result.insert(kind, converted);
+        }
     }
 
     Ok(LimitsFile { inner: result })
@@ -377,7 +898,8 @@ mod test {
 
         let categorizable = HashSet::new();
         let mut arena = SearchableArena::new();
-        parse_limits_file_from_str(&mut arena, &limits_str, &categorizable).unwrap();
+        parse_limits_file_from_str(&mut arena, limits_str, &categorizable, &HashSet::new())
+            .unwrap();
     }
 
     #[test]
@@ -389,7 +911,8 @@ mod test {
 
         let categorizable = HashSet::new();
         let mut arena = SearchableArena::new();
-        parse_limits_file_from_str(&mut arena, &limits_str, &categorizable).unwrap();
+        parse_limits_file_from_str(&mut arena, limits_str, &categorizable, &HashSet::new())
+            .unwrap();
     }
 
     #[test]
@@ -401,9 +924,11 @@ mod test {
         let mut arena = SearchableArena::new();
         let gcc_kind = Kind::new(arena.insert("gcc".to_owned()));
         let categorizable = HashSet::new();
-        let limits = parse_limits_file_from_str(&mut arena, &limits_str, &categorizable).unwrap();
+        let limits =
+            parse_limits_file_from_str(&mut arena, limits_str, &categorizable, &HashSet::new())
+                .unwrap();
 
-        assert_eq!(limits.get_limit(&gcc_kind), Some(&Limit::Number(Some(1))));
+        assert_eq!(limits.get_limit(&gcc_kind), Some(&Limit::Number(Threshold::warnings(Some(1)))));
     }
 
     #[test]
@@ -419,14 +944,17 @@ mod test {
         let mut categorizable = HashSet::new();
         categorizable.insert(gcc_kind.clone());
         let limits =
-            parse_limits_file_from_str(&mut arena, &limits_str, &categorizable).expect("parse");
+            parse_limits_file_from_str(&mut arena, limits_str, &categorizable, &HashSet::new())
+                .expect("parse");
 
         let cat_bad_code = Category::new(arena.get_id("-Wbad-code").expect("bad code"));
         let cat_pedantic = Category::new(arena.get_id("-Wpedantic").expect("pedantic"));
-        let expected_mapping: LinkedHashMap<Category, Option<u64>> =
-            vec![(cat_bad_code, Some(1)), (cat_pedantic, Some(2))]
-                .into_iter()
-                .collect();
+        let expected_mapping: LinkedHashMap<Category, Threshold> = vec![
+            (cat_bad_code, Threshold::warnings(Some(1))),
+            (cat_pedantic, Threshold::warnings(Some(2))),
+        ]
+        .into_iter()
+        .collect();
         assert_eq!(
             limits.get_limit(&gcc_kind),
             Some(&Limit::PerCategory(expected_mapping))
@@ -445,7 +973,8 @@ mod test {
         let mut arena = SearchableArena::new();
         arena.insert("gcc".to_owned());
         let categorizable = HashSet::new();
-        parse_limits_file_from_str(&mut arena, &limits_str, &categorizable).expect("parse");
+        parse_limits_file_from_str(&mut arena, limits_str, &categorizable, &HashSet::new())
+            .expect("parse");
     }
 
     #[test]
@@ -461,13 +990,16 @@ mod test {
         let mut categorizable = HashSet::new();
         categorizable.insert(gcc_kind.clone());
         let limits =
-            parse_limits_file_from_str(&mut arena, &limits_str, &categorizable).expect("parse");
+            parse_limits_file_from_str(&mut arena, limits_str, &categorizable, &HashSet::new())
+                .expect("parse");
 
         let cat_bad_code = Category::new(arena.get_id("-Wbad-code").expect("bad code"));
-        let expected_mapping: LinkedHashMap<Category, Option<u64>> =
-            vec![(cat_bad_code, Some(2)), (Category::none(), Some(1))]
-                .into_iter()
-                .collect();
+        let expected_mapping: LinkedHashMap<Category, Threshold> = vec![
+            (cat_bad_code, Threshold::warnings(Some(2))),
+            (Category::none(), Threshold::warnings(Some(1))),
+        ]
+        .into_iter()
+        .collect();
         assert_eq!(
             limits.get_limit(&gcc_kind),
             Some(&Limit::PerCategory(expected_mapping))
@@ -487,13 +1019,16 @@ mod test {
         let mut categorizable = HashSet::new();
         categorizable.insert(gcc_kind.clone());
         let limits =
-            parse_limits_file_from_str(&mut arena, &limits_str, &categorizable).expect("parse");
+            parse_limits_file_from_str(&mut arena, limits_str, &categorizable, &HashSet::new())
+                .expect("parse");
 
         let cat_bad_code = Category::new(arena.get_id("-Wbad-code").expect("bad code"));
-        let expected_mapping: LinkedHashMap<Category, Option<u64>> =
-            vec![(cat_bad_code, None), (Category::none(), Some(1))]
-                .into_iter()
-                .collect();
+        let expected_mapping: LinkedHashMap<Category, Threshold> = vec![
+            (cat_bad_code, Threshold::warnings(None)),
+            (Category::none(), Threshold::warnings(Some(1))),
+        ]
+        .into_iter()
+        .collect();
         assert_eq!(
             limits.get_limit(&gcc_kind),
             Some(&Limit::PerCategory(expected_mapping))
@@ -514,13 +1049,16 @@ mod test {
         let mut categorizable = HashSet::new();
         categorizable.insert(gcc_kind.clone());
         let limits =
-            parse_limits_file_from_str(&mut arena, &limits_str, &categorizable).expect("parse");
+            parse_limits_file_from_str(&mut arena, limits_str, &categorizable, &HashSet::new())
+                .expect("parse");
 
         let cat_bad_code = Category::new(arena.get_id("-Wbad-code").expect("bad code"));
-        let expected_mapping: LinkedHashMap<Category, Option<u64>> =
-            vec![(cat_bad_code, Some(2)), (Category::none(), Some(1))]
-                .into_iter()
-                .collect();
+        let expected_mapping: LinkedHashMap<Category, Threshold> = vec![
+            (cat_bad_code, Threshold::warnings(Some(2))),
+            (Category::none(), Threshold::warnings(Some(1))),
+        ]
+        .into_iter()
+        .collect();
         assert_eq!(
             limits.get_limit(&gcc_kind),
             Some(&Limit::PerCategory(expected_mapping))
@@ -540,7 +1078,8 @@ mod test {
         let mut categorizable = HashSet::new();
         categorizable.insert(gcc_kind.clone());
         let mut limits =
-            parse_limits_file_from_str(&mut arena, &limits_str, &categorizable).expect("parse");
+            parse_limits_file_from_str(&mut arena, limits_str, &categorizable, &HashSet::new())
+                .expect("parse");
 
         limits.prune_categories();
 
@@ -564,7 +1103,8 @@ mod test {
         let mut categorizable = HashSet::new();
         categorizable.insert(gcc_kind.clone());
         let mut limits =
-            parse_limits_file_from_str(&mut arena, &limits_str, &categorizable).expect("parse");
+            parse_limits_file_from_str(&mut arena, limits_str, &categorizable, &HashSet::new())
+                .expect("parse");
 
         limits.prune_categories();
 
@@ -577,6 +1117,33 @@ _ = 1
         );
     }
 
+    #[test]
+    fn update_leaves_categories_without_a_limit_alone() {
+        let limits_str = r#"
+        [gcc]
+        -Wfoo = 3
+        "#;
+
+        let mut arena = SearchableArena::new();
+        let gcc_kind = Kind::new(arena.insert("gcc".to_owned()));
+        let mut categorizable = HashSet::new();
+        categorizable.insert(gcc_kind.clone());
+        let mut limits =
+            parse_limits_file_from_str(&mut arena, limits_str, &categorizable, &HashSet::new())
+                .expect("parse");
+
+        // The warning counts towards the default limit of gcc in Wcnt.toml instead
+        let category = Category::from_str("-Wbar", &mut arena);
+        let entry = LimitsEntry::new(None::<PathBuf>, gcc_kind, category);
+        let count = EntryCount::new(&entry, Threshold::warnings(Some(5)), 1, Vec::new());
+        limits.update_limits(&count);
+
+        assert_eq!(
+            "[gcc]\n-Wfoo = 3\n",
+            toml::ser::to_string(&limits.as_serializable(&arena)).expect("Deserialize")
+        );
+    }
+
     #[test]
     fn prune_turn_all_zero_into_simple() {
         let limits_str = r#"
@@ -590,7 +1157,8 @@ _ = 1
         let mut categorizable = HashSet::new();
         categorizable.insert(gcc_kind.clone());
         let mut limits =
-            parse_limits_file_from_str(&mut arena, &limits_str, &categorizable).expect("parse");
+            parse_limits_file_from_str(&mut arena, limits_str, &categorizable, &HashSet::new())
+                .expect("parse");
 
         limits.prune_categories();
 
@@ -600,4 +1168,415 @@ _ = 1
             toml::ser::to_string(&limits.as_serializable(&arena)).expect("Deserialize")
         );
     }
+
+    #[test]
+    fn can_deserialize_files_metric() {
+        let limits_str = r#"
+        flake8 = { files = 12 }
+
+        [gcc]
+        -Wbad-code = { files = inf }
+        _ = 3
+        "#;
+
+        let mut arena = SearchableArena::new();
+        let gcc_kind = Kind::new(arena.insert("gcc".to_owned()));
+        let flake8_kind = Kind::new(arena.insert("flake8".to_owned()));
+        let mut categorizable = HashSet::new();
+        categorizable.insert(gcc_kind.clone());
+        let limits =
+            parse_limits_file_from_str(&mut arena, limits_str, &categorizable, &HashSet::new())
+                .expect("parse");
+
+        assert_eq!(
+            limits.get_limit(&flake8_kind),
+            Some(&Limit::Number(Threshold::files(Some(12))))
+        );
+        let cat_bad_code = Category::new(arena.get_id("-Wbad-code").expect("bad code"));
+        let expected_mapping: LinkedHashMap<Category, Threshold> = vec![
+            (cat_bad_code, Threshold::files(None)),
+            (Category::none(), Threshold::warnings(Some(3))),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            limits.get_limit(&gcc_kind),
+            Some(&Limit::PerCategory(expected_mapping))
+        );
+    }
+
+    #[test]
+    fn files_metric_survives_serialization() {
+        let limits_str = r#"
+        flake8 = { files = 12 }
+
+        [gcc]
+        -Wbad-code = { files = 2 }
+        _ = 3
+        "#;
+
+        let mut arena = SearchableArena::new();
+        let gcc_kind = Kind::new(arena.insert("gcc".to_owned()));
+        let flake8_kind = Kind::new(arena.insert("flake8".to_owned()));
+        let mut categorizable = HashSet::new();
+        categorizable.insert(gcc_kind.clone());
+        let limits =
+            parse_limits_file_from_str(&mut arena, limits_str, &categorizable, &HashSet::new())
+                .expect("parse");
+
+        let as_string =
+            toml::ser::to_string(&limits.as_serializable(&arena)).expect("Serialize");
+        let reparsed =
+            parse_limits_file_from_str(&mut arena, &as_string, &categorizable, &HashSet::new())
+                .expect("reparse");
+        assert_eq!(limits.get_limit(&flake8_kind), reparsed.get_limit(&flake8_kind));
+
+        // Tables are written last, so the order of the categories may change
+        fn as_unordered(limit: Option<&Limit>) -> HashMap<Category, Threshold> {
+            match limit {
+                Some(Limit::PerCategory(dict)) => dict.clone().into_iter().collect(),
+                _ => panic!("Expected per category limits"),
+            }
+        }
+        assert_eq!(
+            as_unordered(limits.get_limit(&gcc_kind)),
+            as_unordered(reparsed.get_limit(&gcc_kind))
+        );
+    }
+
+    #[test]
+    fn score_metric_survives_serialization() {
+        let limits_str = r#"
+        flake8 = { score = 40, max_per_file = 4 }
+        "#;
+
+        let mut arena = SearchableArena::new();
+        let flake8_kind = Kind::new(arena.insert("flake8".to_owned()));
+        let limits =
+            parse_limits_file_from_str(&mut arena, limits_str, &HashSet::new(), &HashSet::new())
+                .expect("parse");
+
+        let mut expected = Threshold::score(Some(40));
+        expected.max_per_file = Some(4);
+        assert_eq!(limits.get_limit(&flake8_kind), Some(&Limit::Number(expected)));
+
+        let as_string =
+            toml::ser::to_string(&limits.as_serializable(&arena)).expect("Serialize");
+        let reparsed =
+            parse_limits_file_from_str(&mut arena, &as_string, &HashSet::new(), &HashSet::new())
+                .expect("reparse");
+        assert_eq!(limits.get_limit(&flake8_kind), reparsed.get_limit(&flake8_kind));
+    }
+
+    #[test]
+    fn can_deserialize_max_per_file() {
+        let limits_str = r#"
+        flake8 = { max_per_file = 5 }
+
+        [gcc]
+        -Wbad-code = { warnings = 10, max_per_file = 2 }
+        _ = { files = 3, max_per_file = 1 }
+        "#;
+
+        let mut arena = SearchableArena::new();
+        let gcc_kind = Kind::new(arena.insert("gcc".to_owned()));
+        let flake8_kind = Kind::new(arena.insert("flake8".to_owned()));
+        let mut categorizable = HashSet::new();
+        categorizable.insert(gcc_kind.clone());
+        let limits =
+            parse_limits_file_from_str(&mut arena, limits_str, &categorizable, &HashSet::new())
+                .expect("parse");
+
+        let mut expected_flake8 = Threshold::warnings(None);
+        expected_flake8.max_per_file = Some(5);
+        assert_eq!(
+            limits.get_limit(&flake8_kind),
+            Some(&Limit::Number(expected_flake8))
+        );
+
+        let cat_bad_code = Category::new(arena.get_id("-Wbad-code").expect("bad code"));
+        let mut expected_bad_code = Threshold::warnings(Some(10));
+        expected_bad_code.max_per_file = Some(2);
+        let mut expected_wildcard = Threshold::files(Some(3));
+        expected_wildcard.max_per_file = Some(1);
+        let expected_mapping: LinkedHashMap<Category, Threshold> = vec![
+            (cat_bad_code, expected_bad_code),
+            (Category::none(), expected_wildcard),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            limits.get_limit(&gcc_kind),
+            Some(&Limit::PerCategory(expected_mapping))
+        );
+
+        let as_string =
+            toml::ser::to_string(&limits.as_serializable(&arena)).expect("Serialize");
+        let reparsed =
+            parse_limits_file_from_str(&mut arena, &as_string, &categorizable, &HashSet::new())
+                .expect("reparse");
+        assert_eq!(limits.get_limit(&flake8_kind), reparsed.get_limit(&flake8_kind));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only one of `warnings`, `files` and `score`")]
+    fn cannot_limit_both_warnings_and_files() {
+        let limits_str = r#"
+        gcc = { warnings = 1, files = 1 }
+        "#;
+
+        let mut arena = SearchableArena::new();
+        arena.insert("gcc".to_owned());
+        let categorizable = HashSet::new();
+        parse_limits_file_from_str(&mut arena, limits_str, &categorizable, &HashSet::new())
+            .unwrap();
+    }
+
+    #[test]
+    fn can_deserialize_per_severity() {
+        let limits_str = r#"
+        [gcc]
+        -Wbad-code = 2
+        _ = 10
+
+        [gcc.error]
+        _ = 0
+        "#;
+
+        let mut arena = SearchableArena::new();
+        let gcc_kind = Kind::new(arena.insert("gcc".to_owned()));
+        let mut categorizable = HashSet::new();
+        categorizable.insert(gcc_kind.clone());
+        let severitizable = categorizable.clone();
+        let limits =
+            parse_limits_file_from_str(&mut arena, limits_str, &categorizable, &severitizable)
+                .expect("parse");
+
+        let cat_bad_code = Category::new(arena.get_id("-Wbad-code").expect("bad code"));
+        let error = Severity::new(arena.get_id("error").expect("error"));
+        let expected_mapping: LinkedHashMap<Category, Threshold> = vec![
+            (cat_bad_code, Threshold::warnings(Some(2))),
+            (Category::none(), Threshold::warnings(Some(10))),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            limits.get_limit(&gcc_kind),
+            Some(&Limit::PerCategory(expected_mapping))
+        );
+        let expected_errors: LinkedHashMap<Category, Threshold> =
+            vec![(Category::none(), Threshold::warnings(Some(0)))]
+                .into_iter()
+                .collect();
+        assert_eq!(
+            limits.get_severity_limit(&gcc_kind, &error),
+            Some(&Limit::PerCategory(expected_errors))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "does not capture `severity`")]
+    fn cannot_deserialize_per_severity_without_capture() {
+        let limits_str = r#"
+        [gcc.error]
+        _ = 0
+        "#;
+
+        let mut arena = SearchableArena::new();
+        let gcc_kind = Kind::new(arena.insert("gcc".to_owned()));
+        let mut categorizable = HashSet::new();
+        categorizable.insert(gcc_kind);
+        parse_limits_file_from_str(&mut arena, limits_str, &categorizable, &HashSet::new())
+            .unwrap();
+    }
+
+    #[test]
+    fn per_severity_survives_pruning_and_serialization() {
+        let limits_str = r#"
+        gcc = 3
+
+        [flake8]
+        _ = 1
+
+        [flake8.error]
+        E999 = 0
+        _ = 0
+        "#;
+
+        let mut arena = SearchableArena::new();
+        arena.insert("gcc".to_owned());
+        let flake8_kind = Kind::new(arena.insert("flake8".to_owned()));
+        let mut categorizable = HashSet::new();
+        categorizable.insert(flake8_kind.clone());
+        let severitizable = categorizable.clone();
+        let mut limits =
+            parse_limits_file_from_str(&mut arena, limits_str, &categorizable, &severitizable)
+                .expect("parse");
+
+        limits.prune_categories();
+
+        assert_eq!(
+            r#"gcc = 3
+
+[flake8]
+_ = 1
+
+[flake8.error]
+_ = 0
+"#,
+            toml::ser::to_string(&limits.as_serializable(&arena)).expect("Serialize")
+        );
+    }
+
+    #[test]
+    fn json_and_yaml_limits_are_parsed_like_toml() {
+        let mut arena = SearchableArena::new();
+        let gcc_kind = Kind::new(arena.insert("gcc".to_owned()));
+        let flake8_kind = Kind::new(arena.insert("flake8".to_owned()));
+        let mut categorizable = HashSet::new();
+        categorizable.insert(gcc_kind.clone());
+
+        let toml_str = r#"
+        flake8 = { files = 12 }
+
+        [gcc]
+        -Wbad-code = inf
+        _ = 3
+        "#;
+        let json_str = r#"
+        {"flake8": {"files": 12}, "gcc": {"-Wbad-code": "inf", "_": 3}}
+        "#;
+        let yaml_str = "flake8:\n  files: 12\ngcc:\n  -Wbad-code: .inf\n  _: 3\n";
+
+        let from_toml = parse_limits_file_from_str(
+            &mut arena,
+            &LimitsFormat::Toml.to_toml(toml_str).expect("toml"),
+            &categorizable,
+            &HashSet::new(),
+        )
+        .expect("parse toml");
+        let other_formats = vec![(LimitsFormat::Json, json_str), (LimitsFormat::Yaml, yaml_str)];
+        for (format, limits_str) in other_formats {
+            let as_toml = format.to_toml(limits_str).expect("convert");
+            let limits =
+                parse_limits_file_from_str(&mut arena, &as_toml, &categorizable, &HashSet::new())
+                    .expect("parse");
+            assert_eq!(limits.get_limit(&flake8_kind), from_toml.get_limit(&flake8_kind));
+            assert_eq!(limits.get_limit(&gcc_kind), from_toml.get_limit(&gcc_kind));
+
+            // And it survives being written back in the same format
+            let written = format.serialize(&limits, &arena).expect("serialize");
+            let reparsed = parse_limits_file_from_str(
+                &mut arena,
+                &format.to_toml(&written).expect("convert back"),
+                &categorizable,
+                &HashSet::new(),
+            )
+            .expect("reparse");
+            assert_eq!(reparsed.get_limit(&gcc_kind), from_toml.get_limit(&gcc_kind));
+        }
+    }
+
+    #[test]
+    fn limits_format_is_decided_by_extension() {
+        assert_eq!(LimitsFormat::of(Path::new("src/Limits.toml")), LimitsFormat::Toml);
+        assert_eq!(LimitsFormat::of(Path::new("src/.wcnt-limits.json")), LimitsFormat::Json);
+        assert_eq!(LimitsFormat::of(Path::new("src/limits.yml")), LimitsFormat::Yaml);
+        assert_eq!(LimitsFormat::of(Path::new("src/limits")), LimitsFormat::Toml);
+    }
+
+    #[test]
+    fn central_limits_are_keyed_by_directory() {
+        let settings_str = r#"
+        [gcc]
+        regex = "..."
+
+        [limits."src"]
+        gcc = 10
+
+        [limits."src/component_c"]
+        gcc = { files = 3 }
+        "#;
+
+        let mut arena = SearchableArena::new();
+        let gcc_kind = Kind::new(arena.insert("gcc".to_owned()));
+        let root = Path::new("/project");
+        let (directories, limits) = parse_central_limits_from_str(
+            &mut arena,
+            root,
+            "Limits.toml",
+            settings_str,
+            &HashSet::new(),
+            &HashSet::new(),
+        )
+        .expect("parse");
+
+        let src_limits = Path::new("/project/src/Limits.toml");
+        let component_limits = Path::new("/project/src/component_c/Limits.toml");
+        assert_eq!(directories.get(src_limits).map(String::as_str), Some("src"));
+        assert_eq!(
+            limits[src_limits].get_limit(&gcc_kind),
+            Some(&Limit::Number(Threshold::warnings(Some(10))))
+        );
+        assert_eq!(
+            limits[component_limits].get_limit(&gcc_kind),
+            Some(&Limit::Number(Threshold::files(Some(3))))
+        );
+    }
+
+    #[test]
+    fn central_limits_are_updated_in_place() {
+        let settings_str = r#"# Our settings
+[gcc]
+regex = "..."
+
+[limits."src"]
+gcc = 10
+
+[limits."src/component_c".gcc]
+-Wpedantic = 3
+_ = inf
+"#;
+
+        let mut arena = SearchableArena::new();
+        let gcc_kind = Kind::new(arena.insert("gcc".to_owned()));
+        let mut categorizable = HashSet::new();
+        categorizable.insert(gcc_kind.clone());
+        let root = Path::new("/project");
+        let (directories, mut limits) = parse_central_limits_from_str(
+            &mut arena,
+            root,
+            "Limits.toml",
+            settings_str,
+            &categorizable,
+            &HashSet::new(),
+        )
+        .expect("parse");
+
+        let src_limits = PathBuf::from("/project/src/Limits.toml");
+        limits.get_mut(&src_limits).unwrap().zero(&vec![&gcc_kind].into_iter().collect());
+        let updated =
+            update_central_limits_in_str(settings_str, &directories, &limits, &arena).unwrap();
+        assert!(updated.starts_with("# Our settings\n[gcc]\nregex = \"...\"\n"));
+        assert!(!updated.contains("[limits.\"src/component_c\"]\n"));
+
+        let (_directories, reparsed) = parse_central_limits_from_str(
+            &mut arena,
+            root,
+            "Limits.toml",
+            &updated,
+            &categorizable,
+            &HashSet::new(),
+        )
+        .expect("reparse");
+        assert_eq!(
+            reparsed[&src_limits].get_limit(&gcc_kind),
+            Some(&Limit::Number(Threshold::warnings(Some(0))))
+        );
+        let component_limits = PathBuf::from("/project/src/component_c/Limits.toml");
+        assert_eq!(
+            reparsed[&component_limits].get_limit(&gcc_kind),
+            limits[&component_limits].get_limit(&gcc_kind)
+        );
+    }
 }
@@ -1,5 +1,5 @@
 // This doesn't seem to work/do anything?
-#![deny(intra_doc_link_resolution_failure)]
+#![deny(rustdoc::broken_intra_doc_links)]
 // I do prefer being explicit sometimes.
 #![allow(clippy::redundant_field_names)]
 //! wcnt (Warning Counter) is a small command line tool to count warnings in files, and map them
@@ -12,26 +12,28 @@
 //! that subtree until a deeper, more specific Limits.toml file is encountered.
 use std::collections::{HashMap, HashSet};
 use std::error::Error;
+use std::fmt::Debug;
 use std::fs::read_to_string;
+use std::io::Read;
 use std::iter::FromIterator;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use clap::{App, Arg};
+use clap::{App, Arg, SubCommand};
 use crossbeam_channel::Receiver;
-use env_logger;
-use globset::{Glob, GlobSet, GlobSetBuilder};
-use log::{debug, trace, warn};
-use serde::export::fmt::Debug;
-use toml;
-
-use crate::limits::{Category, Limit, LimitsEntry, LimitsFile};
-use crate::search_for_files::{FileData, IgnoreWalker, LogFile};
-use crate::search_in_files::{FileSystemReader, LogSearchResults};
-use crate::settings::{Kind, Settings};
+use log::{debug, info, trace, warn};
+
+use crate::limits::{CentralLimits, Category, Limit, LimitsEntry, LimitsFile, Metric, Threshold};
+use crate::search_for_files::{FileData, IgnoreWalker, LogFile, LogGlobs};
+use crate::search_in_files::{FileSystemReader, LogSearchResults, PipedLog, Stale};
+use crate::settings::{CentralLimitsSource, Kind, OutsideTree, Settings, StaleCheck, Weights};
 use crate::utils::SearchableArena;
 use crate::warnings::{CountsTowardsLimit, EntryCount, FinalTally};
 
+mod archives;
+mod compression;
 mod limits;
+mod prefilter;
+mod run_command;
 mod search_for_files;
 mod search_in_files;
 mod settings;
@@ -40,20 +42,25 @@ mod warnings;
 
 /// Flattens the mapping of [LimitsFile](struct.LimitsFile.html)s to a more efficient representation
 /// using [Limit Entries](struct.LimitsEntry.html).
-fn flatten_limits(raw_form: &HashMap<PathBuf, LimitsFile>) -> HashMap<LimitsEntry, Option<u64>> {
-    let mut result: HashMap<LimitsEntry, Option<u64>> = HashMap::new();
+fn flatten_limits(raw_form: &HashMap<PathBuf, LimitsFile>) -> HashMap<LimitsEntry, Threshold> {
+    let mut result: HashMap<LimitsEntry, Threshold> = HashMap::new();
     for (path, data) in raw_form {
-        for (kind, entry) in data.iter() {
+        for (kind, severity, entry) in data.iter() {
             match entry {
                 Limit::Number(x) => {
                     result.insert(
-                        LimitsEntry::new(Some(path), kind.clone(), Category::none()),
+                        LimitsEntry::new(Some(path), kind.clone(), Category::none())
+                            .with_severity(severity.clone()),
                         *x,
                     );
                 }
                 Limit::PerCategory(cats) => {
                     for (cat, x) in cats {
-                        result.insert(LimitsEntry::new(Some(path), kind.clone(), cat.clone()), *x);
+                        result.insert(
+                            LimitsEntry::new(Some(path), kind.clone(), cat.clone())
+                                .with_severity(severity.clone()),
+                            *x,
+                        );
                     }
                 }
             }
@@ -68,6 +75,11 @@ struct Arguments {
     start_dir: PathBuf,
     config_file: PathBuf,
     only_kinds: Option<Vec<String>>,
+    logs: Vec<PathBuf>,
+    log_kinds: Option<Vec<String>>,
+    stdin_kinds: Option<Vec<String>>,
+    command: Option<Vec<String>>,
+    command_kinds: Option<Vec<String>>,
     verbosity: u64,
     update_limits: bool,
     prune_limits: bool,
@@ -75,6 +87,11 @@ struct Arguments {
 }
 
 impl Arguments {
+    /// The directory of the config file, which relative paths in the config file start from.
+    fn config_dir(&self) -> &Path {
+        self.config_file.parent().unwrap_or_else(|| Path::new("."))
+    }
+
     fn is_verbose(&self) -> bool {
         self.verbosity > 0
     }
@@ -115,6 +132,37 @@ fn parse_args() -> Result<Arguments, std::io::Error> {
                 .help("Use this config file. (Instead of <start>/Wcnt.toml)")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("log")
+                .long("log")
+                .display_order(4)
+                .value_name("PATH")
+                .help("Search this log file, instead of looking for log files. (May be repeated)")
+                .multiple(true)
+                .number_of_values(1)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("kind")
+                .long("kind")
+                .display_order(5)
+                .value_name("KIND")
+                .help("The kinds of warnings in the --log files. (Instead of matching their files)")
+                .requires("log")
+                .multiple(true)
+                .number_of_values(1)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("stdin")
+                .long("stdin")
+                .display_order(6)
+                .value_name("KIND")
+                .help("Also search what is piped to stdin for these kinds of warnings.")
+                .multiple(true)
+                .number_of_values(1)
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("verbose")
                 .short("v")
@@ -144,6 +192,27 @@ fn parse_args() -> Result<Arguments, std::io::Error> {
                 .requires("update_limits")
                 .takes_value(false),
         )
+        .subcommand(
+            SubCommand::with_name("run")
+                .about("Run a build command, and search its output for warnings.")
+                .arg(
+                    Arg::with_name("kind")
+                        .long("kind")
+                        .value_name("KIND")
+                        .help("The kinds of warnings in the output. (Instead of all kinds)")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("command")
+                        .value_name("COMMAND")
+                        .help("The command to run, after --")
+                        .required(true)
+                        .multiple(true)
+                        .last(true),
+                ),
+        )
         .get_matches();
 
     let start_dir = matches
@@ -158,6 +227,7 @@ fn parse_args() -> Result<Arguments, std::io::Error> {
         .unwrap_or_else(|| start_dir.join("Wcnt.toml"));
 
     let verbosity = matches.occurrences_of("verbose");
+    let run_matches = matches.subcommand_matches("run");
 
     Ok(Arguments {
         start_dir: start_dir,
@@ -165,6 +235,17 @@ fn parse_args() -> Result<Arguments, std::io::Error> {
         verbosity: verbosity,
         only_kinds: matches.values_of("only")
             .map(|vs| Vec::from_iter(vs.map(String::from))),
+        logs: matches.values_of_os("log")
+            .map(|vs| Vec::from_iter(vs.map(PathBuf::from)))
+            .unwrap_or_default(),
+        log_kinds: matches.values_of("kind")
+            .map(|vs| Vec::from_iter(vs.map(String::from))),
+        stdin_kinds: matches.values_of("stdin")
+            .map(|vs| Vec::from_iter(vs.map(String::from))),
+        command: run_matches.and_then(|m| m.values_of("command"))
+            .map(|vs| Vec::from_iter(vs.map(String::from))),
+        command_kinds: run_matches.and_then(|m| m.values_of("kind"))
+            .map(|vs| Vec::from_iter(vs.map(String::from))),
         print_all: matches.is_present("print_all"),
         update_limits: matches.is_present("update_limits"),
         prune_limits: matches.is_present("prune_limits"),
@@ -186,76 +267,377 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     settings.configure_kinds_to_run(&args.only_kinds);
 
+    let mut piped_logs = Vec::new();
+    if let Some(names) = &args.stdin_kinds {
+        piped_logs.push(read_piped_log(&settings, names)?);
+    }
+    // Run the build first, so we also find the log files it writes
+    let failed_command = match &args.command {
+        Some(command) => {
+            let (log, status) = run_command_log(&settings, command, &args.command_kinds)?;
+            piped_logs.push(log);
+            Some(status).filter(|status| !status.success())
+        }
+        None => None,
+    };
+
+    let result = run_checks(&args, settings, piped_logs, failed_command.is_none());
+    match failed_command {
+        None => {
+            if !result? {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Some(status) => {
+            if let Err(e) = result {
+                eprintln!("Error: {}", e);
+            }
+            eprintln!("The command failed with {}.", status);
+            std::process::exit(status.code().unwrap_or(1));
+        }
+    }
+}
+
+/// Find and search the log files, along with the `piped_logs`, and check the warnings against the
+/// limits. Returns whether the limits were kept. Only update the limits, if asked to, when
+/// `may_update_limits`.
+fn run_checks(
+    args: &Arguments,
+    mut settings: Settings,
+    piped_logs: Vec<PipedLog>,
+    may_update_limits: bool,
+) -> Result<bool, Box<dyn Error>> {
     let globset = construct_types_info(&settings)?;
     let categorizables = settings.categorizables();
+    let severitizables = settings.severitizables();
 
     debug!("Starting with these settings: {}", settings.display());
 
-    let rx = search_for_files::construct_file_searcher::<IgnoreWalker>(&args.start_dir, globset);
-    let (log_files, limits) =
-        collect_file_results(&mut settings.string_arena, &categorizables, rx)?;
+    // With centralized limits, there are no limits files to look for in the tree
+    let limits_file_name = settings.limits_file_name().to_owned();
+    let limits_to_search_for = match settings.central_limits() {
+        Some(_) => None,
+        None => Some(limits_file_name.as_str()),
+    };
+    // Log files given on the command line replace looking for them
+    let explicit_logs = explicit_log_files(&settings, args, &globset)?;
+    let (log_roots, globset) = if args.logs.is_empty() {
+        (log_roots(&settings, args)?, globset)
+    } else {
+        (Vec::new(), HashMap::new())
+    };
+    let rx = search_for_files::construct_file_searcher::<IgnoreWalker>(
+        &args.start_dir,
+        limits_to_search_for,
+        &log_roots,
+        globset,
+    );
+    let (mut log_files, mut limits) =
+        collect_file_results(&mut settings.string_arena, &categorizables, &severitizables, rx)?;
+    // Log roots may overlap
+    log_files.extend(explicit_logs);
+    log_files.sort();
+    log_files.dedup();
+    check_required_log_files(&settings, &count_log_files_per_kind(&log_files, &piped_logs))?;
+
+    let central_limits = match settings.central_limits().cloned() {
+        Some(source) => {
+            let file = match source {
+                CentralLimitsSource::Settings => args.config_file.clone(),
+                CentralLimitsSource::File(file) => args.config_dir().join(file),
+            };
+            let (central, central_limits) = CentralLimits::parse(
+                &mut settings.string_arena,
+                &file,
+                &limits_file_name,
+                &categorizables,
+                &severitizables,
+            )?;
+            limits.extend(central_limits);
+            Some(central)
+        }
+        None => None,
+    };
 
     for (path, limits_file) in &limits {
         debug!("Found Limits.toml file at `{}`", path.display());
         trace!("{}", limits_file.display(&settings.string_arena));
+        check_no_limits_for_group_members(&settings, path, limits_file)?;
     }
 
     let rx = search_in_files::search_files::<FileSystemReader>(
         &settings,
+        &args.start_dir,
         &limits
             .keys()
             .cloned()
             .collect::<HashSet<_>>(),
         log_files,
+        piped_logs,
     );
 
     // Flatten the limit entries to make it easier to match
     // Construct {limits_file}:{kind}:{category} -> u64  mapping
-    let results_tmp = gather_results_from_logs(&mut settings.string_arena, rx);
-    let flat_limits = flatten_limits(&limits);
+    let (results_tmp, stale) = gather_results_from_logs(&mut settings.string_arena, rx)?;
+    let dropped_stale_logs = report_stale_findings(&settings, stale)?;
+    let mut flat_limits = flatten_limits(&limits);
 
-    let results = remap_to_actual_limit_entries(&settings.string_arena, &flat_limits, results_tmp);
+    if settings.is_strict() {
+        check_culprits_are_covered(&settings, &results_tmp)?;
+    }
+    let results_tmp = apply_outside_tree_policy(&settings, &mut flat_limits, results_tmp)?;
+    let mut results =
+        remap_to_actual_limit_entries(&settings.string_arena, &flat_limits, results_tmp);
+    add_default_limits(&settings, &mut flat_limits, &results);
+    add_global_totals(&settings, &args.config_file, &mut flat_limits, &mut results);
 
     // Finally, check the results and report any violations
-    let tally = check_warnings_against_thresholds(&flat_limits, &results);
+    let tally = check_warnings_against_thresholds(&flat_limits, settings.weights(), &results);
     let violations = tally.violations();
     if args.print_all {
         report_tally_results(
-            &args,
+            args,
             &settings.string_arena,
             &results,
-            &tally.non_violations(),
+            tally.non_violations(),
         );
     }
 
     if !violations.is_empty() {
         report_tally_results(
-            &args,
+            args,
             &settings.string_arena,
             &results,
-            &violations,
+            violations,
         );
         eprintln!(
             "Found {} violations against specified limits.",
             violations.len()
         );
-        std::process::exit(1);
+        Ok(false)
     } else {
-        if args.update_limits {
-            update_limits(&settings, &limits, &tally, args.prune_limits)?;
+        if args.update_limits && !may_update_limits {
+            eprintln!("Not updating limits, since the command failed.");
+        } else if args.update_limits && dropped_stale_logs {
+            // The warnings of the dropped log files would otherwise be missing from the new limits
+            eprintln!("Not updating limits, since the warnings of stale log files were dropped.");
+        } else if args.update_limits {
+            update_limits(
+                &settings,
+                &limits,
+                central_limits.as_ref(),
+                &tally,
+                args.prune_limits,
+            )?;
+        }
+        Ok(true)
+    }
+}
+
+/// The log files given with `--log`. They have the kinds given with `--kind`, or else the kinds
+/// whose file globs match them.
+fn explicit_log_files(
+    settings: &Settings,
+    args: &Arguments,
+    types: &HashMap<Kind, LogGlobs>,
+) -> Result<Vec<LogFile>, Box<dyn Error>> {
+    let given_kinds = match &args.log_kinds {
+        Some(names) => Some(kinds_named(settings, names, "--kind")?),
+        None => None,
+    };
+
+    let mut result = Vec::new();
+    for log in &args.logs {
+        let path = log
+            .canonicalize()
+            .map_err(|e| format!("Could not find log file `{}`: {}", log.display(), e))?;
+        let mut kinds = match &given_kinds {
+            Some(kinds) => kinds.clone(),
+            None => types
+                .iter()
+                .filter(|(_kind, globs)| globs.is_match(&path))
+                .map(|(kind, _globs)| kind.clone())
+                .collect(),
+        };
+        if kinds.is_empty() {
+            let msg = format!(
+                "Log file `{}` matches the files of no kind, use --kind to give its kinds.",
+                log.display()
+            );
+            return Err(msg.into());
         }
+        kinds.sort();
+        result.push(LogFile::new(path, kinds));
+    }
+    Ok(result)
+}
+
+/// The kinds with the given `names`, which were given with the command line `option`.
+fn kinds_named(
+    settings: &Settings,
+    names: &[String],
+    option: &str,
+) -> Result<Vec<Kind>, Box<dyn Error>> {
+    let mut result = Vec::new();
+    for name in names {
+        let kind = settings
+            .string_arena
+            .get_id(name)
+            .map(Kind::new)
+            .filter(|kind| settings.get(kind).is_some())
+            .ok_or_else(|| format!("Unknown kind `{}` given with {}.", name, option))?;
+        result.push(kind);
+    }
+    Ok(result)
+}
+
+/// Read the log piped to stdin, which has the kinds with the given `names`. It is decoded for each
+/// kind when searched, like log files.
+fn read_piped_log(
+    settings: &Settings,
+    names: &[String],
+) -> Result<PipedLog, Box<dyn Error>> {
+    let mut kinds = kinds_named(settings, names, "--stdin")?;
+    kinds.sort();
+    let mut contents = Vec::new();
+    std::io::stdin()
+        .read_to_end(&mut contents)
+        .map_err(|e| format!("Could not read log from stdin: {}", e))?;
+    Ok((LogFile::new(PathBuf::from(STDIN_LOG_PATH), kinds), contents))
+}
+
+/// Run the `command`, and capture its output as a log with the kinds with the given `names`, or
+/// else of all kinds.
+fn run_command_log(
+    settings: &Settings,
+    command: &[String],
+    names: &Option<Vec<String>>,
+) -> Result<(PipedLog, std::process::ExitStatus), Box<dyn Error>> {
+    let mut kinds = match names {
+        Some(names) => kinds_named(settings, names, "run --kind")?,
+        None => settings.kinds().cloned().collect(),
+    };
+    kinds.sort();
+    let (contents, status) = run_command::run_and_capture(command)
+        .map_err(|e| format!("Could not run command `{}`: {}", command.join(" "), e))?;
+    Ok(((LogFile::new(PathBuf::from(COMMAND_LOG_PATH), kinds), contents), status))
+}
+
+/// The path of the log piped to stdin, as it is reported.
+const STDIN_LOG_PATH: &str = "<stdin>";
+
+/// The path of the output of the command run by wcnt, as it is reported.
+const COMMAND_LOG_PATH: &str = "<command>";
+
+/// The `log_roots` of the settings, relative to the config file.
+fn log_roots(settings: &Settings, args: &Arguments) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut result = Vec::new();
+    for root in settings.log_roots() {
+        let path = args.config_dir().join(root);
+        let path = path
+            .canonicalize()
+            .map_err(|e| format!("Could not find log root `{}`: {}", path.display(), e))?;
+        result.push(path);
+    }
+    Ok(result)
+}
+
+/// Count the log files found for every [Kind](struct.Kind.html), including the `piped_logs`. Empty
+/// log files are not counted, as they can not tell us anything.
+fn count_log_files_per_kind<'a>(
+    log_files: &'a [LogFile],
+    piped_logs: &'a [PipedLog],
+) -> HashMap<&'a Kind, usize> {
+    let mut result = HashMap::new();
+    for (log_file, contents) in piped_logs {
+        if contents.is_empty() {
+            warn!("The log `{}` is empty", log_file.path().display());
+        } else {
+            for kind in log_file.kinds() {
+                *result.entry(kind).or_insert(0) += 1;
+            }
+        }
+    }
+    for log_file in log_files {
+        // Entries in archives are not on the file system, and empty ones were left out when
+        // listing the entries of their archive
+        let is_in_archive = archives::split_entry_path(log_file.path()).is_some();
+        // Compressed log files are empty if there is nothing in them once decompressed
+        let is_empty = !is_in_archive
+            && std::fs::File::open(log_file.path())
+                .and_then(|file| compression::is_empty(std::io::BufReader::new(file)))
+                .unwrap_or(true);
+        if is_empty {
+            warn!("Log file `{}` is empty", log_file.path().display());
+            continue;
+        }
+        for kind in log_file.kinds() {
+            *result.entry(kind).or_insert(0) += 1;
+        }
+    }
+    result
+}
+
+/// Kinds may require a minimum number of log files, so a missing log does not silently count as
+/// zero warnings. Report all kinds with too few log files as an error.
+fn check_required_log_files(
+    settings: &Settings,
+    log_files_per_kind: &HashMap<&Kind, usize>,
+) -> Result<(), Box<dyn Error>> {
+    let mut missing = Vec::new();
+    for kind in settings.kinds() {
+        let field = settings.get(kind).expect("Infallible lookup");
+        let found = log_files_per_kind.get(kind).cloned().unwrap_or(0);
+        if found < field.min_files {
+            missing.push(format!(
+                "  {}: found {} non-empty log files matching [{}], but requires {}",
+                kind.to_str(&settings.string_arena),
+                found,
+                field.files.join(", "),
+                field.min_files
+            ));
+        }
+    }
+    if missing.is_empty() {
         Ok(())
+    } else {
+        Err(format!("Missing log files:\n{}", missing.join("\n")).into())
+    }
+}
+
+/// Warnings of kinds in a group count towards the limits of the group, so any limits for the kinds
+/// themselves would never be used. Rather than silently ignoring them, refuse to run.
+fn check_no_limits_for_group_members(
+    settings: &Settings,
+    path: &Path,
+    limits_file: &LimitsFile,
+) -> Result<(), Box<dyn Error>> {
+    for (kind, _severity, _limit) in limits_file.iter() {
+        if let Some(group) = settings.group_of(kind) {
+            return Err(format!(
+                "`{}` has limits for kind `{}`, which counts towards group `{}`. Limit the group \
+                 instead.",
+                path.display(),
+                kind.to_str(&settings.string_arena),
+                group.to_str(&settings.string_arena),
+            )
+            .into());
+        }
     }
+    Ok(())
 }
 
-/// Update `Limits.toml` files with new, lower limits.
+/// Update `Limits.toml` files with new, lower limits. Centralized limits are all written back to
+/// the one file they came from.
 fn update_limits(
     settings: &Settings,
     limits: &HashMap<PathBuf, LimitsFile>,
+    central_limits: Option<&CentralLimits>,
     tally: &FinalTally,
     aggressive_pruning: bool,
 ) -> Result<(), Box<dyn Error>> {
-    let kinds_to_update: HashSet<_> = settings.kinds().collect();
+    let kinds_to_update = settings.limit_kinds();
     let mut updated = HashSet::new();
     let mut limits_copy: HashMap<PathBuf, LimitsFile> = limits.clone();
     for lf in limits_copy.values_mut() {
@@ -265,8 +647,10 @@ fn update_limits(
     for entry_count in tally.non_violations() {
         let entry = entry_count.entry();
         if let Some(limits_path) = &entry.limits_file {
-            let limit_file = limits_copy.get_mut(limits_path).expect("Infallible lookup");
-            limit_file.update_limits(&entry_count);
+            // Project wide totals are counted towards Wcnt.toml, which we never update
+            if let Some(limit_file) = limits_copy.get_mut(limits_path) {
+                limit_file.update_limits(entry_count);
+            }
         }
     }
     if aggressive_pruning {
@@ -281,36 +665,65 @@ fn update_limits(
         }
     }
 
+    if let Some(central) = central_limits {
+        if !updated.is_empty() {
+            println!("Updating `{}`", central.path().display());
+            central.write(&limits_copy, &settings.string_arena)?;
+        }
+        return Ok(());
+    }
+
     for path in updated {
         let limit_file = limits_copy.get(path).expect("Did not find copy?");
-        let as_string = toml::to_string(&limit_file.as_serializable(&settings.string_arena))?;
         println!("Updating `{}`", path.display());
-        std::fs::write(path, as_string)?;
+        limits::write_limits_file(path, limit_file, &settings.string_arena)?;
     }
     Ok(())
 }
 
-/// Because the LimitEntries from the warnings use the category from the warning pass, it might
-/// always map to an actual user defined warning. This pass lookup the actual warnings and ensure
-/// that we have a user defined limit when doing later comparisons.
+/// Because the LimitEntries from the warnings use the category and severity from the warning pass,
+/// it might always map to an actual user defined warning. This pass lookup the actual warnings and
+/// ensure that we have a user defined limit when doing later comparisons.
 fn remap_to_actual_limit_entries(
     arena: &SearchableArena,
-    defined_limits: &HashMap<LimitsEntry, Option<u64>>,
+    defined_limits: &HashMap<LimitsEntry, Threshold>,
     found: HashMap<LimitsEntry, HashSet<CountsTowardsLimit>>,
 ) -> HashMap<LimitsEntry, HashSet<CountsTowardsLimit>> {
     let mut result = HashMap::new();
+    let mut defaulted = HashSet::new();
 
     for (limit, warnings) in found {
-        let key = if defined_limits.contains_key(&limit) {
-            limit
-        } else if defined_limits.contains_key(&limit.without_category()) {
-            limit.without_category()
-        } else {
-            for w in &warnings {
-                warn!("Could not handle warning: \"{}\"", w.display(arena));
+        // Limits for a specific severity takes precedence over those for any severity
+        let candidates = [
+            limit.clone(),
+            limit.without_category(),
+            limit.without_severity(),
+            limit.without_severity().without_category(),
+        ];
+        let key = match candidates.iter().find(|c| defined_limits.contains_key(c)) {
+            Some(defined) => defined.clone(),
+            None => {
+                let key = limit.without_severity().without_category();
+                match &limit.limits_file {
+                    None => {
+                        for w in &warnings {
+                            warn!("Could not handle warning: \"{}\"", w.display(arena));
+                        }
+                        warn!("Are this/these file(s) outside your source tree?");
+                    }
+                    // Counted towards the default limit of the kind, which is declared in Wcnt.toml
+                    Some(limits_file) => {
+                        if defaulted.insert(key.clone()) {
+                            info!(
+                                "Warnings of '{}' without a limit in `{}` use its default limit",
+                                limit.kind.to_str(arena),
+                                limits_file.display()
+                            );
+                        }
+                    }
+                }
+                key
             }
-            warn!("Are this/these file(s) outside your source tree?");
-            limit.without_category()
         };
 
         result
@@ -321,11 +734,174 @@ fn remap_to_actual_limit_entries(
     result
 }
 
+/// In strict mode, every culprit must be covered by a limits file. Report the directories of the
+/// culprits that are not as a configuration error. Kinds which `ignore` warnings outside the source
+/// tree have opted out of this.
+fn check_culprits_are_covered(
+    settings: &Settings,
+    found: &HashMap<LimitsEntry, HashSet<CountsTowardsLimit>>,
+) -> Result<(), Box<dyn Error>> {
+    let mut uncovered: Vec<&Path> = found
+        .iter()
+        .filter(|(entry, _warnings)| entry.limits_file.is_none())
+        .flat_map(|(_entry, warnings)| warnings)
+        .filter(|w| {
+            let field = settings.get(w.kind()).expect("Warnings are only found for known kinds");
+            field.outside_tree != OutsideTree::Ignore
+        })
+        .map(|w| w.culprit().parent().unwrap_or_else(|| Path::new("")))
+        .collect();
+    if uncovered.is_empty() {
+        return Ok(());
+    }
+    uncovered.sort();
+    uncovered.dedup();
+    let listing: Vec<String> = uncovered
+        .iter()
+        .map(|dir| format!("  {}", dir.display()))
+        .collect();
+    Err(format!(
+        "Strict mode: these directories have warnings, but are not covered by any limits file:\n{}",
+        listing.join("\n")
+    )
+    .into())
+}
+
+/// Give every entry in `results` without a declared limit the default limit of its
+/// [Kind](struct.Kind.html).
+fn add_default_limits(
+    settings: &Settings,
+    flat_limits: &mut HashMap<LimitsEntry, Threshold>,
+    results: &HashMap<LimitsEntry, HashSet<CountsTowardsLimit>>,
+) {
+    for entry in results.keys() {
+        if !flat_limits.contains_key(entry) {
+            let threshold = Threshold::warnings(settings.default_limit(&entry.kind));
+            flat_limits.insert(entry.clone(), threshold);
+        }
+    }
+}
+
+/// Handle the warnings whose culprits are outside the source tree, according to the `outside_tree`
+/// policy of their [Kind](struct.Kind.html). Counted warnings are kept apart for each Kind, even in
+/// a group, and are given their `outside_tree_limit` in `flat_limits`. Any warnings of Kinds which
+/// should fail are reported, and turned into an error.
+fn apply_outside_tree_policy(
+    settings: &Settings,
+    flat_limits: &mut HashMap<LimitsEntry, Threshold>,
+    found: HashMap<LimitsEntry, HashSet<CountsTowardsLimit>>,
+) -> Result<HashMap<LimitsEntry, HashSet<CountsTowardsLimit>>, Box<dyn Error>> {
+    let mut result = HashMap::new();
+    let mut failures = Vec::new();
+    for (entry, warnings) in found {
+        if entry.limits_file.is_some() {
+            result
+                .entry(entry)
+                .or_insert_with(HashSet::new)
+                .extend(warnings);
+            continue;
+        }
+        for w in warnings {
+            let field = settings.get(w.kind()).expect("Warnings are only found for known kinds");
+            match field.outside_tree {
+                OutsideTree::Ignore => {
+                    debug!(
+                        "Ignoring warning outside the source tree: \"{}\"",
+                        w.display(&settings.string_arena)
+                    );
+                }
+                OutsideTree::Fail => failures.push(w),
+                OutsideTree::Count => {
+                    let outside_entry =
+                        LimitsEntry::new(None::<PathBuf>, w.kind().clone(), Category::none());
+                    let limit = match field.outside_tree_limit {
+                        Some(x) => Some(x),
+                        None => settings.default_limit(w.kind()),
+                    };
+                    flat_limits
+                        .entry(outside_entry.clone())
+                        .or_insert_with(|| Threshold::warnings(limit));
+                    result
+                        .entry(outside_entry)
+                        .or_insert_with(HashSet::new)
+                        .insert(w);
+                }
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        failures.sort();
+        for w in &failures {
+            eprintln!("Outside source tree: {}", w.display(&settings.string_arena));
+        }
+        return Err(format!(
+            "Found {} warnings for files outside the source tree.",
+            failures.len()
+        )
+        .into());
+    }
+    Ok(result)
+}
+
+/// Sum up the warnings for every [Kind](struct.Kind.html), or group of kinds, with a project wide
+/// `max_total`, across all Limits.toml files. These sums are added to `results` as entries
+/// belonging to the `config_file`, so they are checked and reported like any other limit. Like in
+/// Limits.toml files, categories without a total fall back to the wildcard, and else to the
+/// default limit of the kind.
+fn add_global_totals(
+    settings: &Settings,
+    config_file: &Path,
+    flat_limits: &mut HashMap<LimitsEntry, Threshold>,
+    results: &mut HashMap<LimitsEntry, HashSet<CountsTowardsLimit>>,
+) {
+    let mut totals: HashMap<LimitsEntry, HashSet<CountsTowardsLimit>> = HashMap::new();
+    let mut kinds_with_totals = HashSet::new();
+    for (kind, limit) in settings.max_totals() {
+        kinds_with_totals.insert(kind);
+        match limit {
+            Limit::Number(x) => {
+                let entry = LimitsEntry::new(Some(config_file), kind.clone(), Category::none());
+                flat_limits.insert(entry.clone(), *x);
+                totals.insert(entry, HashSet::new());
+            }
+            Limit::PerCategory(cats) => {
+                for (cat, x) in cats {
+                    let entry = LimitsEntry::new(Some(config_file), kind.clone(), cat.clone());
+                    flat_limits.insert(entry.clone(), *x);
+                    totals.insert(entry, HashSet::new());
+                }
+            }
+        }
+    }
+
+    for w in results.values().flatten() {
+        // Warnings of kinds in a group count towards the total of the group
+        let kind = settings.group_of(w.kind()).unwrap_or_else(|| w.kind());
+        if !kinds_with_totals.contains(kind) {
+            continue;
+        }
+        let entry = LimitsEntry::new(Some(config_file), kind.clone(), w.category().clone());
+        let key = if totals.contains_key(&entry) {
+            entry
+        } else {
+            entry.without_category()
+        };
+        totals
+            .entry(key)
+            .or_default()
+            .insert(w.clone());
+    }
+    add_default_limits(settings, flat_limits, &totals);
+    results.extend(totals);
+}
+
 type LogAndLimitFiles = (Vec<LogFile>, HashMap<PathBuf, LimitsFile>);
 /// Read from the channel producing file results and gather them up into lists.
 fn collect_file_results(
     arena: &mut SearchableArena,
     categorizables: &HashSet<Kind>,
+    severitizables: &HashSet<Kind>,
     rx: Receiver<FileData>,
 ) -> Result<LogAndLimitFiles, Box<dyn Error>> {
     let mut log_files = Vec::with_capacity(256);
@@ -336,7 +912,8 @@ fn collect_file_results(
                 log_files.push(log_file);
             }
             FileData::LimitsFile(path) => {
-                let limit = limits::parse_limits_file(arena, &path, categorizables)?;
+                let limit =
+                    limits::parse_limits_file(arena, &path, categorizables, severitizables)?;
                 limits.insert(path, limit);
             }
         }
@@ -345,37 +922,49 @@ fn collect_file_results(
 }
 
 /// Print the found [EntryCount](struct.EntryCount.html)s based on the verbosity level found in
-/// [Arguments](struct.Arguments.html)
+/// [Arguments](struct.Arguments.html). Files exceeding `max_per_file` are printed at any verbosity.
 fn report_tally_results(
     args: &Arguments,
     arena: &SearchableArena,
     results: &HashMap<LimitsEntry, HashSet<CountsTowardsLimit>>,
     tally: &[EntryCount],
 ) {
-    if args.is_verbose() {
-        for counted_entry in tally {
-            println!("{}", counted_entry.display(&arena));
-            if args.is_very_verbose() {
-                let warnings = results.get(counted_entry.entry()).expect("Got the key from here..");
-                let mut warnings_vec: Vec<&CountsTowardsLimit> = Vec::with_capacity(warnings.len());
-                warnings_vec.extend(warnings.iter());
-                warnings_vec.sort();
-                for w in &warnings_vec {
-                    println!("  => {}", w.display(&arena));
-                }
+    for counted_entry in tally {
+        // Files exceeding `max_per_file` are what needs fixing, so always show them
+        if !args.is_verbose() && counted_entry.hotspots().is_empty() {
+            continue;
+        }
+        println!("{}", counted_entry.display(arena));
+        for (culprit, count) in counted_entry.hotspots() {
+            println!("  !! {} has {} warnings", culprit.display(), count);
+        }
+        // Warnings outside the source tree can not be found through any limits file, so
+        // always show them
+        let is_outside_tree = counted_entry.entry().limits_file.is_none();
+        if args.is_very_verbose() || (args.is_verbose() && is_outside_tree) {
+            let warnings = results.get(counted_entry.entry()).expect("Got the key from here..");
+            let mut warnings_vec: Vec<&CountsTowardsLimit> = Vec::with_capacity(warnings.len());
+            warnings_vec.extend(warnings.iter());
+            warnings_vec.sort();
+            for w in &warnings_vec {
+                println!("  => {}", w.display(arena));
             }
         }
     }
 }
 
+type ResultsAndStaleFindings = (HashMap<LimitsEntry, HashSet<CountsTowardsLimit>>, Vec<Stale>);
 /// Read from the channel producing [Log Search Result](struct.LogSearchResult.html)s and gather
 /// them in sets, removing duplicates and grouping them per appropriate
-/// [LimitsEntry](struct.LimitsEntry.html).
+/// [LimitsEntry](struct.LimitsEntry.html). Fails if any log file is incomplete, since a truncated
+/// log would look like an improvement. Stale findings are returned along with the results.
 fn gather_results_from_logs(
     arena: &mut SearchableArena,
     rx: Receiver<Result<LogSearchResults, (LogFile, std::io::Error)>>,
-) -> HashMap<LimitsEntry, HashSet<CountsTowardsLimit>> {
+) -> Result<ResultsAndStaleFindings, Box<dyn Error>> {
     let mut results: HashMap<LimitsEntry, HashSet<CountsTowardsLimit>> = HashMap::new();
+    let mut incomplete = Vec::new();
+    let mut stale = Vec::new();
     for search_result_result in rx {
         let search_result = match search_result_result {
             Ok(r) => r,
@@ -389,14 +978,69 @@ fn gather_results_from_logs(
                 continue;
             }
         };
+        if let Some((kind, log_file)) = &search_result.incomplete {
+            incomplete.push(format!(
+                "  {} (no completion marker for `{}`)",
+                log_file.display(),
+                kind.to_str(arena)
+            ));
+        }
+        stale.extend(search_result.stale.iter().cloned());
         for (entry, warnings) in process_search_results(arena, search_result) {
             results
                 .entry(entry)
-                .or_insert_with(HashSet::new)
+                .or_default()
                 .extend(warnings);
         }
     }
-    results
+    if incomplete.is_empty() {
+        Ok((results, stale))
+    } else {
+        incomplete.sort();
+        Err(format!("Incomplete log files:\n{}", incomplete.join("\n")).into())
+    }
+}
+
+/// Print a summary of the stale findings, unless there are none, and fail if any of them should
+/// fail the run, according to the settings. Returns whether the warnings of any stale log file were
+/// dropped.
+fn report_stale_findings(
+    settings: &Settings,
+    mut stale: Vec<Stale>,
+) -> Result<bool, Box<dyn Error>> {
+    if stale.is_empty() {
+        return Ok(false);
+    }
+    // A log file searched for several kinds is checked once per kind
+    stale.sort();
+    stale.dedup();
+
+    eprintln!("Found {} stale findings:", stale.len());
+    let mut should_fail = false;
+    let mut dropped_logs = false;
+    for finding in &stale {
+        let check = match finding {
+            Stale::Log { .. } => settings.stale_logs(),
+            Stale::Culprit { .. } => settings.missing_culprits(),
+        };
+        let action = match check {
+            StaleCheck::Drop => {
+                dropped_logs |= matches!(finding, Stale::Log { .. });
+                ", dropping its warnings"
+            }
+            StaleCheck::Fail => {
+                should_fail = true;
+                ""
+            }
+            StaleCheck::Warn | StaleCheck::Ignore => "",
+        };
+        eprintln!("  {}{}", finding, action);
+    }
+    if should_fail {
+        Err("Found stale log files or missing culprits.".into())
+    } else {
+        Ok(dropped_logs)
+    }
 }
 
 /// Process a single [Log Search Result](../search_in_files/struct.LogSearchResult.html) and gather all warnings that
@@ -410,14 +1054,15 @@ fn process_search_results(
     let mut results = HashMap::new();
 
     for (mut limits_entry, warnings) in search_result.warnings {
-        limits_entry.category.remap_id(&incoming_arena, &arena);
+        limits_entry.category.remap_id(&incoming_arena, arena);
+        limits_entry.severity.remap_id(&incoming_arena, arena);
         results
             .entry(limits_entry)
             .or_insert_with(HashSet::new)
             .extend(
                 warnings
                     .into_iter()
-                    .map(|w| w.remap(&incoming_arena, &arena)),
+                    .map(|w| w.remap(&incoming_arena, arena)),
             );
     }
     results
@@ -427,34 +1072,52 @@ fn process_search_results(
 /// against the declared [limits](../limits/struct.LimitsEntry.html), resulting in a
 /// [FinalTally](../warnings/struct.FinalTally.html).
 fn check_warnings_against_thresholds<'entries, 'x>(
-    flat_limits: &'x HashMap<LimitsEntry, Option<u64>>,
+    flat_limits: &'x HashMap<LimitsEntry, Threshold>,
+    weights: &'x Weights,
     results: &'entries HashMap<LimitsEntry, HashSet<CountsTowardsLimit>>,
 ) -> FinalTally<'entries> {
     let mut tally = FinalTally::new(results.len());
     for (limits_entry, warnings) in results {
-        let num_warnings = warnings.len() as u64;
-        let threshold = match flat_limits.get(&limits_entry) {
+        let threshold = match flat_limits.get(limits_entry) {
             Some(x) => *x,
-            None => Some(0),
+            None => Threshold::warnings(Some(0)),
+        };
+        let per_culprit = count_per_culprit(warnings);
+        let actual = match threshold.metric {
+            Metric::Warnings => warnings.len() as u64,
+            Metric::Files => per_culprit.len() as u64,
+            Metric::Score => warnings.iter().map(|w| weights.weight_of(w)).sum(),
         };
-        tally.add(EntryCount::new(limits_entry, threshold, num_warnings));
+        let hotspots = match threshold.max_per_file {
+            Some(max_per_file) => per_culprit
+                .into_iter()
+                .filter(|(_culprit, count)| *count > max_per_file)
+                .collect(),
+            None => Vec::new(),
+        };
+        tally.add(EntryCount::new(limits_entry, threshold, actual, hotspots));
     }
     tally
 }
 
+/// Group the `warnings` by their culprit file, and count how many warnings each culprit has.
+fn count_per_culprit(warnings: &HashSet<CountsTowardsLimit>) -> HashMap<&Path, u64> {
+    let mut result = HashMap::new();
+    for w in warnings {
+        *result.entry(w.culprit()).or_insert(0) += 1;
+    }
+    result
+}
+
 /// Gather the glob patterns from the [Settings](struct.Settings.html) and create a mapping from
 /// [Kind](struct.Kind.html) to patterns. So the later search step can figure out what regexes to
 /// use when searching through the file.
 fn construct_types_info(
     settings_dict: &Settings,
-) -> Result<HashMap<Kind, GlobSet>, Box<dyn Error>> {
+) -> Result<HashMap<Kind, LogGlobs>, Box<dyn Error>> {
     let mut result = HashMap::new();
     for (warning_t, warning_info) in settings_dict.iter() {
-        let mut glob_builder = GlobSetBuilder::new();
-        for file_glob in &warning_info.files {
-            glob_builder.add(Glob::new(file_glob)?);
-        }
-        result.insert(warning_t.clone(), glob_builder.build()?);
+        result.insert(warning_t.clone(), LogGlobs::new(&warning_info.files)?);
     }
     Ok(result)
 }
@@ -463,6 +1126,7 @@ fn construct_types_info(
 mod test {
     use std::num::NonZeroUsize;
 
+    use crate::limits::Severity;
     use crate::warnings::Description;
 
     use super::*;
@@ -522,6 +1186,8 @@ mod test {
             LogSearchResults {
                 string_arena: arena_2,
                 warnings: dict,
+                incomplete: None,
+                stale: Vec::new(),
             }
         };
 
@@ -607,6 +1273,8 @@ mod test {
             LogSearchResults {
                 string_arena: first_arena,
                 warnings: dict,
+                incomplete: None,
+                stale: Vec::new(),
             }
         };
 
@@ -618,6 +1286,8 @@ mod test {
             LogSearchResults {
                 string_arena: second_arena,
                 warnings: dict,
+                incomplete: None,
+                stale: Vec::new(),
             }
         };
 
@@ -626,7 +1296,7 @@ mod test {
         tx.send(Ok(search_result2)).unwrap();
         drop(tx);
         // Act
-        let results = gather_results_from_logs(&mut main_arena, rx);
+        let (results, _stale) = gather_results_from_logs(&mut main_arena, rx).unwrap();
 
         // Assert
         let main_category_code = Category::new(main_arena.get_id("-Wbad-code").unwrap());
@@ -682,7 +1352,7 @@ mod test {
             Category::none(),
         );
         let mut defined_limits = HashMap::new();
-        defined_limits.insert(defined_limit_entry.clone(), Some(1));
+        defined_limits.insert(defined_limit_entry.clone(), Threshold::warnings(Some(1)));
         let processed_results = remap_to_actual_limit_entries(&main_arena, &defined_limits, results);
         assert_ne!(expected_result, processed_results);
 
@@ -693,4 +1363,488 @@ mod test {
             .extend(vec![expected_interface_warning, expected_code_warning]);
         assert_eq!(expected_processed_results, processed_results);
     }
+
+    fn warning_at(kind: &Kind, file: &str, line: usize) -> CountsTowardsLimit {
+        CountsTowardsLimit::new(
+            PathBuf::from(file),
+            NonZeroUsize::new(line),
+            None,
+            kind.clone(),
+            Category::none(),
+            Description::none(),
+        )
+    }
+
+    #[test]
+    fn files_metric_counts_distinct_culprits() {
+        let mut arena = SearchableArena::new();
+        let kind = Kind::new(arena.insert("kind".to_owned()));
+        let entry = LimitsEntry::new(Some("/tmp/Limits.toml"), kind.clone(), Category::none());
+
+        let mut results = HashMap::new();
+        results.insert(
+            entry.clone(),
+            vec![
+                warning_at(&kind, "/tmp/src/a.c", 1),
+                warning_at(&kind, "/tmp/src/a.c", 2),
+                warning_at(&kind, "/tmp/src/b.c", 1),
+            ]
+            .into_iter()
+            .collect::<HashSet<_>>(),
+        );
+
+        let mut flat_limits = HashMap::new();
+        flat_limits.insert(entry.clone(), Threshold::files(Some(2)));
+        let tally = check_warnings_against_thresholds(&flat_limits, &Weights::default(), &results);
+        assert!(tally.violations().is_empty());
+        assert_eq!(tally.non_violations()[0].actual, 2);
+
+        flat_limits.insert(entry, Threshold::warnings(Some(2)));
+        let tally = check_warnings_against_thresholds(&flat_limits, &Weights::default(), &results);
+        assert_eq!(tally.violations()[0].actual, 3);
+    }
+
+    #[test]
+    fn max_per_file_reports_hotspots() {
+        let mut arena = SearchableArena::new();
+        let kind = Kind::new(arena.insert("kind".to_owned()));
+        let entry = LimitsEntry::new(Some("/tmp/Limits.toml"), kind.clone(), Category::none());
+
+        let mut results = HashMap::new();
+        results.insert(
+            entry.clone(),
+            vec![
+                warning_at(&kind, "/tmp/src/a.c", 1),
+                warning_at(&kind, "/tmp/src/a.c", 2),
+                warning_at(&kind, "/tmp/src/a.c", 3),
+                warning_at(&kind, "/tmp/src/b.c", 1),
+            ]
+            .into_iter()
+            .collect::<HashSet<_>>(),
+        );
+
+        let mut threshold = Threshold::warnings(Some(10));
+        threshold.max_per_file = Some(2);
+        let mut flat_limits = HashMap::new();
+        flat_limits.insert(entry, threshold);
+        let tally = check_warnings_against_thresholds(&flat_limits, &Weights::default(), &results);
+        assert_eq!(tally.violations().len(), 1);
+        assert_eq!(
+            tally.violations()[0].hotspots(),
+            &[(Path::new("/tmp/src/a.c"), 3)]
+        );
+    }
+
+    #[test]
+    fn score_metric_sums_weights() {
+        let settings_str = r#"
+        [kind]
+        regex = "^(?P<file>[^:]+): (?P<severity>\\w+) \\[(?P<category>.+)\\]$"
+        files = ["**/foo.txt"]
+        weight = 2
+        severity_weights = { error = 5 }
+        "#;
+        let mut settings = toml::from_str::<Settings>(settings_str).unwrap();
+        let kind = Kind::new(settings.string_arena.get_id("kind").unwrap());
+        let error = Severity::new(settings.string_arena.get_id("error").unwrap());
+        let note = Severity::new(settings.string_arena.insert("note".to_owned()));
+        let entry = LimitsEntry::new(Some("/tmp/Limits.toml"), kind.clone(), Category::none());
+
+        let mut results = HashMap::new();
+        results.insert(
+            entry.clone(),
+            vec![
+                warning_at(&kind, "/tmp/src/a.c", 1).with_severity(error),
+                warning_at(&kind, "/tmp/src/a.c", 2).with_severity(note),
+            ]
+            .into_iter()
+            .collect::<HashSet<_>>(),
+        );
+
+        let mut flat_limits = HashMap::new();
+        flat_limits.insert(entry.clone(), Threshold::score(Some(12)));
+        let tally = check_warnings_against_thresholds(&flat_limits, settings.weights(), &results);
+        assert!(tally.violations().is_empty());
+        assert_eq!(tally.non_violations()[0].actual, 2 * 5 + 2);
+
+        flat_limits.insert(entry, Threshold::score(Some(11)));
+        let tally = check_warnings_against_thresholds(&flat_limits, settings.weights(), &results);
+        assert_eq!(tally.violations().len(), 1);
+    }
+
+    #[test]
+    fn stale_findings_fail_the_run_only_if_configured() {
+        let stale = vec![
+            Stale::Culprit {
+                log_file: PathBuf::from("/tmp/build.log"),
+                culprit: PathBuf::from("/tmp/src/deleted.c"),
+            },
+            Stale::Log {
+                log_file: PathBuf::from("/tmp/build.log"),
+                newer: PathBuf::from("/tmp/src/Limits.toml"),
+            },
+        ];
+        let settings = toml::from_str::<Settings>(r#"stale_logs = "warn""#).unwrap();
+        report_stale_findings(&settings, stale.clone()).unwrap();
+
+        let settings = toml::from_str::<Settings>(r#"missing_culprits = "fail""#).unwrap();
+        let err = report_stale_findings(&settings, stale).unwrap_err();
+        assert_eq!(err.to_string(), "Found stale log files or missing culprits.");
+    }
+
+    #[test]
+    fn outside_tree_policy_decides_what_happens_to_warnings() {
+        let settings_str = r#"
+        [ignored]
+        regex = "^(?P<file>[^:]+)$"
+        files = ["**/foo.txt"]
+        outside_tree = "ignore"
+
+        [counted]
+        regex = "^(?P<file>[^:]+)$"
+        files = ["**/foo.txt"]
+        outside_tree_limit = 1
+
+        [failing]
+        regex = "^(?P<file>[^:]+)$"
+        files = ["**/foo.txt"]
+        outside_tree = "fail"
+        "#;
+        let settings = toml::from_str::<Settings>(settings_str).unwrap();
+        let kind = |name: &str| Kind::new(settings.string_arena.get_id(name).unwrap());
+        let found_for = |kinds: &[Kind]| {
+            let mut found = HashMap::new();
+            for k in kinds {
+                found.insert(
+                    LimitsEntry::new(None::<PathBuf>, k.clone(), Category::none()),
+                    vec![warning_at(k, "/usr/include/x.h", 1), warning_at(k, "/usr/include/x.h", 2)]
+                        .into_iter()
+                        .collect::<HashSet<_>>(),
+                );
+            }
+            found
+        };
+
+        let mut flat_limits = HashMap::new();
+        let found = found_for(&[kind("ignored"), kind("counted")]);
+        let results = apply_outside_tree_policy(&settings, &mut flat_limits, found).unwrap();
+        let counted_entry = LimitsEntry::new(None::<PathBuf>, kind("counted"), Category::none());
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[&counted_entry].len(), 2);
+        assert_eq!(flat_limits[&counted_entry], Threshold::warnings(Some(1)));
+
+        let found = found_for(&[kind("failing")]);
+        assert!(apply_outside_tree_policy(&settings, &mut flat_limits, found).is_err());
+    }
+
+    #[test]
+    fn default_limits_apply_without_declared_limits() {
+        let settings_str = r#"
+        strict = true
+
+        [lenient]
+        regex = "^(?P<file>[^:]+)$"
+        files = ["**/foo.txt"]
+        default_limit = inf
+
+        [strict]
+        regex = "^(?P<file>[^:]+)$"
+        files = ["**/foo.txt"]
+        "#;
+        let settings = toml::from_str::<Settings>(settings_str).unwrap();
+        let lenient = Kind::new(settings.string_arena.get_id("lenient").unwrap());
+        let strict = Kind::new(settings.string_arena.get_id("strict").unwrap());
+
+        let limits_file = Some("/tmp/Limits.toml");
+        let lenient_entry = LimitsEntry::new(limits_file, lenient.clone(), Category::none());
+        let strict_entry = LimitsEntry::new(limits_file, strict.clone(), Category::none());
+        let mut results = HashMap::new();
+        results.insert(
+            lenient_entry.clone(),
+            vec![warning_at(&lenient, "/tmp/src/a.c", 1)].into_iter().collect::<HashSet<_>>(),
+        );
+        results.insert(
+            strict_entry.clone(),
+            vec![warning_at(&strict, "/tmp/src/a.c", 1)].into_iter().collect::<HashSet<_>>(),
+        );
+        check_culprits_are_covered(&settings, &results).unwrap();
+
+        let mut flat_limits = HashMap::new();
+        add_default_limits(&settings, &mut flat_limits, &results);
+        assert_eq!(flat_limits[&lenient_entry], Threshold::warnings(None));
+        assert_eq!(flat_limits[&strict_entry], Threshold::warnings(Some(0)));
+
+        results.insert(
+            LimitsEntry::new(None::<PathBuf>, strict.clone(), Category::none()),
+            vec![warning_at(&strict, "/usr/include/x.h", 1)].into_iter().collect::<HashSet<_>>(),
+        );
+        let err = check_culprits_are_covered(&settings, &results).unwrap_err();
+        assert!(err.to_string().ends_with("not covered by any limits file:\n  /usr/include"));
+    }
+
+    #[test]
+    fn explicit_log_files_get_kinds_from_arguments_or_globs() {
+        let settings_str = r#"
+        [cargo]
+        regex = "^(?P<file>[^:]+)$"
+        files = ["**/Cargo.toml"]
+
+        [other]
+        regex = "^(?P<file>[^:]+)$"
+        files = ["**/other.log"]
+        "#;
+        let settings = toml::from_str::<Settings>(settings_str).unwrap();
+        let types = construct_types_info(&settings).unwrap();
+        let cargo = Kind::new(settings.string_arena.get_id("cargo").unwrap());
+        let other = Kind::new(settings.string_arena.get_id("other").unwrap());
+        let mut args = Arguments {
+            start_dir: PathBuf::from("."),
+            config_file: PathBuf::from("Wcnt.toml"),
+            only_kinds: None,
+            logs: vec![PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml")],
+            log_kinds: None,
+            stdin_kinds: None,
+            command: None,
+            command_kinds: None,
+            verbosity: 0,
+            update_limits: false,
+            prune_limits: false,
+            print_all: false,
+        };
+
+        let log_files = explicit_log_files(&settings, &args, &types).unwrap();
+        assert_eq!(log_files[0].kinds(), &[cargo]);
+
+        args.log_kinds = Some(vec!["other".to_owned()]);
+        let log_files = explicit_log_files(&settings, &args, &types).unwrap();
+        assert_eq!(log_files[0].kinds(), &[other]);
+
+        args.log_kinds = Some(vec!["unknown".to_owned()]);
+        let err = explicit_log_files(&settings, &args, &types).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown kind `unknown` given with --kind.");
+    }
+
+    #[test]
+    fn required_kinds_must_have_log_files() {
+        let settings_str = r#"
+        [optional]
+        regex = "^(?P<file>[^:]+)$"
+        files = ["**/optional.txt"]
+
+        [required]
+        regex = "^(?P<file>[^:]+)$"
+        files = ["**/required.txt"]
+        required = true
+
+        [many]
+        regex = "^(?P<file>[^:]+)$"
+        files = ["**/many.txt"]
+        min_files = 2
+        "#;
+        let settings = toml::from_str::<Settings>(settings_str).unwrap();
+        let required = Kind::new(settings.string_arena.get_id("required").unwrap());
+        let many = Kind::new(settings.string_arena.get_id("many").unwrap());
+
+        let mut counts = HashMap::new();
+        counts.insert(&required, 1);
+        counts.insert(&many, 2);
+        check_required_log_files(&settings, &counts).unwrap();
+
+        counts.remove(&required);
+        counts.insert(&many, 1);
+        let err = check_required_log_files(&settings, &counts).unwrap_err().to_string();
+        assert!(err.contains("required: found 0 non-empty log files matching [**/required.txt]"));
+        assert!(err.contains(
+            "many: found 1 non-empty log files matching [**/many.txt], but requires 2"
+        ));
+        assert!(!err.contains("optional"));
+
+        // A non-empty piped log counts as a log file
+        let stdin_log = LogFile::new(PathBuf::from(STDIN_LOG_PATH), vec![required.clone()]);
+        let piped_logs = vec![(stdin_log, b"x".to_vec())];
+        let counts = count_log_files_per_kind(&[], &piped_logs);
+        assert_eq!(counts.get(&required), Some(&1));
+    }
+
+    #[test]
+    fn limits_are_not_updated_when_stale_logs_are_dropped() {
+        let dir = std::env::temp_dir().join(format!("wcnt-stale-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config_file = dir.join("Wcnt.toml");
+        let settings_str = r#"
+        stale_logs = "drop"
+
+        [kind]
+        regex = "^(?P<file>[^:\n]+):(?P<line>\\d+): (?P<description>.+)$"
+        files = ["**/build.log"]
+        "#;
+        std::fs::write(&config_file, settings_str).unwrap();
+        let log = dir.join("build.log");
+        std::fs::write(&log, "a.c:1: unused variable\n").unwrap();
+        // The log is from before the limits were last changed
+        let an_hour_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+        let log_file = std::fs::OpenOptions::new().write(true).open(&log).unwrap();
+        log_file.set_modified(an_hour_ago).unwrap();
+        std::fs::write(dir.join("a.c"), "").unwrap();
+        let limits_file = dir.join("Limits.toml");
+        std::fs::write(&limits_file, "kind = 5\n").unwrap();
+
+        let args = Arguments {
+            start_dir: dir.clone(),
+            config_file: config_file,
+            only_kinds: None,
+            logs: Vec::new(),
+            log_kinds: None,
+            stdin_kinds: None,
+            command: None,
+            command_kinds: None,
+            verbosity: 0,
+            update_limits: true,
+            prune_limits: false,
+            print_all: false,
+        };
+        let settings = toml::from_str::<Settings>(settings_str).unwrap();
+        let kept_limits = run_checks(&args, settings, Vec::new(), true).unwrap();
+        let limits = std::fs::read_to_string(&limits_file).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(kept_limits);
+        // Dropping the warnings of the log must not lower the limits to 0
+        assert_eq!(limits, "kind = 5\n");
+    }
+
+    #[test]
+    fn global_totals_sum_warnings_across_limits_files() {
+        let settings_str = r#"
+        [kind]
+        regex = "^(?P<file>[^:]+)$"
+        files = ["**/foo.txt"]
+        max_total = 2
+        "#;
+        let settings = toml::from_str::<Settings>(settings_str).unwrap();
+        let kind = Kind::new(settings.string_arena.get_id("kind").unwrap());
+
+        let entry_a = LimitsEntry::new(Some("/tmp/a/Limits.toml"), kind.clone(), Category::none());
+        let entry_b = LimitsEntry::new(Some("/tmp/b/Limits.toml"), kind.clone(), Category::none());
+        let mut flat_limits = HashMap::new();
+        flat_limits.insert(entry_a.clone(), Threshold::warnings(Some(2)));
+        flat_limits.insert(entry_b.clone(), Threshold::warnings(Some(2)));
+
+        let mut results = HashMap::new();
+        results.insert(
+            entry_a,
+            vec![warning_at(&kind, "/tmp/a/x.c", 1), warning_at(&kind, "/tmp/a/x.c", 2)]
+                .into_iter()
+                .collect::<HashSet<_>>(),
+        );
+        results.insert(
+            entry_b,
+            vec![warning_at(&kind, "/tmp/b/y.c", 1)]
+                .into_iter()
+                .collect::<HashSet<_>>(),
+        );
+
+        let config_file = Path::new("/tmp/Wcnt.toml");
+        add_global_totals(&settings, config_file, &mut flat_limits, &mut results);
+        let tally = check_warnings_against_thresholds(&flat_limits, &Weights::default(), &results);
+
+        let global_entry = LimitsEntry::new(Some(config_file), kind, Category::none());
+        assert_eq!(tally.violations().len(), 1);
+        assert_eq!(tally.violations()[0].entry(), &global_entry);
+        assert_eq!(tally.violations()[0].actual, 3);
+    }
+
+    #[test]
+    fn global_totals_count_groups_and_fall_back_to_the_default_limit() {
+        let settings_str = r#"
+        [gcc]
+        regex = "^(?P<file>[^:]+):(?P<category>.+)$"
+        files = ["**/gcc.log"]
+        group = "c-compilers"
+        max_total = { -Wfoo = 2 }
+        default_limit = 1
+
+        [clang]
+        regex = "^(?P<file>[^:]+):(?P<category>.+)$"
+        files = ["**/clang.log"]
+        group = "c-compilers"
+        default_limit = 1
+        "#;
+        let mut settings = toml::from_str::<Settings>(settings_str).unwrap();
+        let gcc = Kind::new(settings.string_arena.get_id("gcc").unwrap());
+        let clang = Kind::new(settings.string_arena.get_id("clang").unwrap());
+        let group = Kind::new(settings.string_arena.get_id("c-compilers").unwrap());
+        let foo = Category::new(settings.string_arena.get_or_insert("-Wfoo"));
+        let bar = Category::new(settings.string_arena.get_or_insert("-Wbar"));
+        let warning = |kind: &Kind, line: usize, category: &Category| {
+            CountsTowardsLimit::new(
+                PathBuf::from("/tmp/a/x.c"),
+                NonZeroUsize::new(line),
+                None,
+                kind.clone(),
+                category.clone(),
+                Description::none(),
+            )
+        };
+
+        let entry = LimitsEntry::new(Some("/tmp/a/Limits.toml"), group.clone(), Category::none());
+        let mut flat_limits = HashMap::new();
+        flat_limits.insert(entry.clone(), Threshold::warnings(None));
+        let mut results = HashMap::new();
+        results.insert(
+            entry,
+            vec![
+                warning(&gcc, 1, &foo),
+                warning(&clang, 2, &foo),
+                warning(&clang, 3, &bar),
+                warning(&clang, 4, &bar),
+            ]
+            .into_iter()
+            .collect::<HashSet<_>>(),
+        );
+
+        let config_file = Path::new("/tmp/Wcnt.toml");
+        add_global_totals(&settings, config_file, &mut flat_limits, &mut results);
+        let tally = check_warnings_against_thresholds(&flat_limits, &Weights::default(), &results);
+
+        // Both kinds count towards the total of the group
+        let foo_entry = LimitsEntry::new(Some(config_file), group.clone(), foo);
+        assert_eq!(results.get(&foo_entry).map(HashSet::len), Some(2));
+        // Without a wildcard, other categories are held to the default limit
+        let rest_entry = LimitsEntry::new(Some(config_file), group, Category::none());
+        assert_eq!(flat_limits.get(&rest_entry), Some(&Threshold::warnings(Some(1))));
+        assert_eq!(tally.violations().len(), 1);
+        assert_eq!(tally.violations()[0].entry(), &rest_entry);
+        assert_eq!(tally.violations()[0].actual, 2);
+    }
+
+    #[test]
+    fn remap_prefers_limits_for_the_same_severity() {
+        let mut arena = SearchableArena::new();
+        let kind = Kind::new(arena.insert("kind".to_owned()));
+        let error = Severity::new(arena.insert("error".to_owned()));
+        let warning = Severity::new(arena.insert("warning".to_owned()));
+
+        let any_entry = LimitsEntry::new(Some("/tmp/Limits.toml"), kind.clone(), Category::none());
+        let error_entry = any_entry.clone().with_severity(error.clone());
+        let mut defined_limits = HashMap::new();
+        defined_limits.insert(any_entry.clone(), Threshold::warnings(Some(10)));
+        defined_limits.insert(error_entry.clone(), Threshold::warnings(Some(0)));
+
+        let an_error = warning_at(&kind, "/tmp/src/a.c", 1).with_severity(error.clone());
+        let a_warning = warning_at(&kind, "/tmp/src/a.c", 2).with_severity(warning.clone());
+        let mut found = HashMap::new();
+        found.insert(
+            any_entry.clone().with_severity(error),
+            vec![an_error.clone()].into_iter().collect::<HashSet<_>>(),
+        );
+        found.insert(
+            any_entry.clone().with_severity(warning),
+            vec![a_warning.clone()].into_iter().collect::<HashSet<_>>(),
+        );
+
+        let results = remap_to_actual_limit_entries(&arena, &defined_limits, found);
+        assert_eq!(results.get(&error_entry), Some(&vec![an_error].into_iter().collect()));
+        assert_eq!(results.get(&any_entry), Some(&vec![a_warning].into_iter().collect()));
+    }
 }
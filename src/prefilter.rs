@@ -0,0 +1,277 @@
+//! Module responsible for finding where in a log file the warnings of several kinds may be, in a
+//! single pass over it.
+//!
+//! Most regexes require some literal text in every warning they match, such as `: warning: `. The
+//! log file is searched for the literals of all kinds at once, which is a lot faster than searching
+//! it with each regex in turn. The regex of a kind without any of its literals in the log file need
+//! not search it at all. When a large log file is searched piece by piece, the regex of a kind only
+//! searches the lines around the literals of that kind, as a warning spans at most
+//! `max_match_lines` lines then.
+use std::ops::Range;
+
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder, MatchKind};
+use regex::Regex;
+use regex_syntax::hir::{Hir, HirKind, Literal, RepetitionKind, RepetitionRange};
+use regex_syntax::ParserBuilder;
+
+/// Literals shorter than this are too common in log files to be worth searching for.
+const MIN_LITERAL_LEN: usize = 3;
+
+/// Finds the regions of a text where the warnings of several kinds may be.
+pub(crate) struct LiteralPrefilter {
+    literals: AhoCorasick,
+    // The kinds requiring each literal
+    kinds_of: Vec<Vec<usize>>,
+    // The most lines a warning of each kind spans, or None if it requires no literal
+    max_match_lines: Vec<Option<usize>>,
+}
+
+impl LiteralPrefilter {
+    /// Build a prefilter for the `regexes` of several kinds, along with the most lines a warning of
+    /// each kind spans. Returns None if none of the regexes requires a literal.
+    pub(crate) fn new(regexes: &[(&Regex, usize)]) -> Option<Self> {
+        let mut literals: Vec<String> = Vec::new();
+        let mut kinds_of: Vec<Vec<usize>> = Vec::new();
+        let mut max_match_lines = Vec::new();
+        for (kind, (regex, lines)) in regexes.iter().enumerate() {
+            match required_literal(regex) {
+                Some(literal) => {
+                    match literals.iter().position(|l| *l == literal) {
+                        Some(i) => kinds_of[i].push(kind),
+                        None => {
+                            literals.push(literal);
+                            kinds_of.push(vec![kind]);
+                        }
+                    }
+                    max_match_lines.push(Some(*lines));
+                }
+                None => max_match_lines.push(None),
+            }
+        }
+        if literals.is_empty() {
+            return None;
+        }
+        // Literals of different kinds may overlap, like `warning: ` and `: warning: `
+        let literals = AhoCorasickBuilder::new()
+            .match_kind(MatchKind::Standard)
+            .dfa(true)
+            .build(&literals);
+        Some(LiteralPrefilter {
+            literals: literals,
+            kinds_of: kinds_of,
+            max_match_lines: max_match_lines,
+        })
+    }
+
+    /// Whether the warnings of each kind may be in the `text`, in order. Always true for a kind
+    /// whose regex requires no literal.
+    pub(crate) fn kinds_found(&self, text: &str) -> Vec<bool> {
+        let mut found: Vec<bool> = self.max_match_lines.iter().map(Option::is_none).collect();
+        for hit in self.literals.find_overlapping_iter(text) {
+            for &kind in &self.kinds_of[hit.pattern()] {
+                found[kind] = true;
+            }
+            if found.iter().all(|&found| found) {
+                break;
+            }
+        }
+        found
+    }
+
+    /// The regions of the `text` where the warnings of each kind may be, in order. None for a kind
+    /// means anywhere, as its regex requires no literal.
+    pub(crate) fn regions(&self, text: &str) -> Vec<Option<Vec<Range<usize>>>> {
+        let mut regions: Vec<Option<Regions>> = self
+            .max_match_lines
+            .iter()
+            .map(|lines| lines.map(Regions::new))
+            .collect();
+        for hit in self.literals.find_overlapping_iter(text) {
+            for &kind in &self.kinds_of[hit.pattern()] {
+                if let Some(regions) = &mut regions[kind] {
+                    regions.add_hit(text, hit.start()..hit.end());
+                }
+            }
+        }
+        regions
+            .into_iter()
+            .map(|regions| regions.map(|r| r.regions))
+            .collect()
+    }
+}
+
+/// The regions of a text around the literals of a kind found in it, covering `lines` lines before
+/// and after each literal, merged where they overlap.
+struct Regions {
+    lines: usize,
+    regions: Vec<Range<usize>>,
+    last_hit_end: usize,
+}
+
+impl Regions {
+    fn new(lines: usize) -> Self {
+        Regions {
+            lines: lines,
+            regions: Vec::new(),
+            last_hit_end: 0,
+        }
+    }
+
+    /// Add the region around the literal found at `hit`. Literals are found in order of where they
+    /// end, so regions only ever grow towards the end of the text, and each part of it is only
+    /// scanned for line breaks once.
+    fn add_hit(&mut self, text: &str, hit: Range<usize>) {
+        let bytes = text.as_bytes();
+        match self.regions.last_mut() {
+            Some(region) if hit.start < region.end => {
+                // Extend the region as many lines as the literal is after the previous one
+                let lines = memchr::memchr_iter(b'\n', &bytes[self.last_hit_end..hit.end]).count();
+                region.end = end_of_lines(bytes, region.end, lines);
+            }
+            last => {
+                let floor = last.map_or(0, |region| region.end);
+                let start = start_of_lines(bytes, hit.start, self.lines, floor);
+                let end = end_of_lines(bytes, hit.end, self.lines);
+                match self.regions.last_mut() {
+                    Some(region) if start <= region.end => region.end = end,
+                    _ => self.regions.push(start..end),
+                }
+            }
+        }
+        self.last_hit_end = hit.end;
+    }
+}
+
+/// The start of the line `lines - 1` lines before the one at `pos`, but not before `floor`.
+fn start_of_lines(bytes: &[u8], pos: usize, lines: usize, floor: usize) -> usize {
+    let mut start = pos;
+    for _ in 0..lines {
+        match memchr::memrchr(b'\n', &bytes[floor..start]) {
+            Some(i) => start = floor + i,
+            None => return floor,
+        }
+    }
+    start + 1
+}
+
+/// The end of the line `lines - 1` lines after the one at `pos`, including its line break. Where
+/// `pos` is if `lines` is 0.
+fn end_of_lines(bytes: &[u8], pos: usize, lines: usize) -> usize {
+    let mut end = pos;
+    for _ in 0..lines {
+        match memchr::memchr(b'\n', &bytes[end..]) {
+            Some(i) => end += i + 1,
+            None => return bytes.len(),
+        }
+    }
+    end
+}
+
+/// The longest literal text contained in every match of the `regex`, if long enough to be worth
+/// searching for.
+fn required_literal(regex: &Regex) -> Option<String> {
+    // Parsed the same way as the regexes of the kinds are built
+    let hir = ParserBuilder::new()
+        .multi_line(true)
+        .build()
+        .parse(regex.as_str())
+        .ok()?;
+    required_literals(&hir)
+        .into_iter()
+        .filter(|literal| literal.chars().count() >= MIN_LITERAL_LEN)
+        .max_by_key(|literal| literal.len())
+}
+
+/// The literals contained in every match of the `hir`. Literals inside alternations, optional
+/// repetitions or case insensitive parts are not.
+fn required_literals(hir: &Hir) -> Vec<String> {
+    match hir.kind() {
+        HirKind::Literal(Literal::Unicode(c)) => vec![c.to_string()],
+        HirKind::Group(group) => required_literals(&group.hir),
+        HirKind::Repetition(repetition) => {
+            let at_least = match &repetition.kind {
+                RepetitionKind::ZeroOrOne | RepetitionKind::ZeroOrMore => 0,
+                RepetitionKind::OneOrMore => 1,
+                RepetitionKind::Range(RepetitionRange::Exactly(n))
+                | RepetitionKind::Range(RepetitionRange::AtLeast(n))
+                | RepetitionKind::Range(RepetitionRange::Bounded(n, _)) => *n,
+            };
+            if at_least > 0 {
+                required_literals(&repetition.hir)
+            } else {
+                Vec::new()
+            }
+        }
+        HirKind::Concat(hirs) => {
+            // Consecutive literal characters make up a longer literal
+            let mut result = Vec::new();
+            let mut literal = String::new();
+            for hir in hirs {
+                match hir.kind() {
+                    HirKind::Literal(Literal::Unicode(c)) => literal.push(*c),
+                    _ => {
+                        if !literal.is_empty() {
+                            result.push(std::mem::take(&mut literal));
+                        }
+                        result.extend(required_literals(hir));
+                    }
+                }
+            }
+            if !literal.is_empty() {
+                result.push(literal);
+            }
+            result
+        }
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn required_literals_are_extracted_from_regexes() {
+        let literal = |regex: &str| required_literal(&Regex::new(regex).unwrap());
+        assert_eq!(
+            literal(r"^(?P<file>[^:]+):(?P<line>\d+): warning: (?P<description>.+)$"),
+            Some(": warning: ".to_owned())
+        );
+        assert_eq!(
+            literal(r"^warning: (?P<description>.+)\n\s+--> (?P<file>[^:]+):(?P<line>\d+)$"),
+            Some("warning: ".to_owned())
+        );
+        assert_eq!(literal(r"^(?P<file>\S+)(?:: (?i)warning)?: (?P<description>.+)$"), None);
+        assert_eq!(literal(r"^(?P<file>[^:]+):(?P<line>\d+)$"), None);
+    }
+
+    #[test]
+    fn regions_surround_the_literals_of_each_kind() {
+        let gcc = Regex::new(r"^(?P<file>[^:\n]+):(?P<line>\d+): warning: (?P<d>.+)$").unwrap();
+        let rustc = Regex::new(r"^warning: (?P<d>.+)\n\s+--> (?P<file>[^:]+)$").unwrap();
+        let anything = Regex::new(r"^(?P<file>.+)$").unwrap();
+        let prefilter = LiteralPrefilter::new(&[(&gcc, 1), (&rustc, 2), (&anything, 1)]).unwrap();
+        let text = "noise\n\
+                    a.c:1: warning: x\n\
+                    noise\n\
+                    noise\n\
+                    warning: y\n\
+                    \x20 --> b.rs\n\
+                    warning: z\n\
+                    \x20 --> c.rs\n\
+                    noise\n";
+        let line = |n: usize| -> usize { text.split_inclusive('\n').take(n).map(str::len).sum() };
+        let (gcc_warning, rustc_warnings) = (line(1)..line(2), line(0)..line(8));
+        assert_eq!(
+            prefilter.regions(text),
+            vec![
+                Some(vec![gcc_warning]),
+                // The literal of gcc contains the literal of rustc, and adjacent regions are merged
+                Some(vec![rustc_warnings]),
+                None,
+            ]
+        );
+        assert_eq!(prefilter.kinds_found(text), vec![true, true, true]);
+        assert_eq!(prefilter.kinds_found("noise\nwarning: y\n"), vec![false, true, true]);
+    }
+}
@@ -0,0 +1,78 @@
+//! Module responsible for running a build command, so its output can be searched for warnings
+//! without first being written to a log file.
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+/// Run the `command`, streaming its stdout and stderr to ours while capturing both, line by line,
+/// as if they had been redirected to the same log. Returns the captured output, still to be
+/// decoded like a log file, along with the exit status of the command.
+pub(crate) fn run_and_capture(command: &[String]) -> io::Result<(Vec<u8>, ExitStatus)> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "No command to run"))?;
+    let mut child = Command::new(program)
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let captured = Arc::new(Mutex::new(Vec::new()));
+    let stdout = child.stdout.take().expect("Stdout of command is piped");
+    let stderr = child.stderr.take().expect("Stderr of command is piped");
+    let stdout_tee = tee(stdout, io::stdout(), captured.clone());
+    let stderr_tee = tee(stderr, io::stderr(), captured.clone());
+    // Reap the command even if copying its output failed, e.g. as our stdout was closed
+    let stdout_copied = stdout_tee.join().expect("Could not join() stdout of command");
+    let stderr_copied = stderr_tee.join().expect("Could not join() stderr of command");
+    let status = child.wait()?;
+    stdout_copied?;
+    stderr_copied?;
+
+    let captured = std::mem::take(&mut *captured.lock().expect("Captured output is poisoned"));
+    Ok((captured, status))
+}
+
+/// Start a thread copying lines `from` the command `to` our output, and to the `captured` output.
+fn tee<R, W>(from: R, mut to: W, captured: Arc<Mutex<Vec<u8>>>) -> JoinHandle<io::Result<()>>
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut reader = BufReader::new(from);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            if reader.read_until(b'\n', &mut line)? == 0 {
+                return Ok(());
+            }
+            to.write_all(&line)?;
+            to.flush()?;
+            captured
+                .lock()
+                .expect("Captured output is poisoned")
+                .extend_from_slice(&line);
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn captures_both_outputs_and_the_exit_status() {
+        let command: Vec<String> = vec!["sh", "-c", "echo out; echo err 1>&2; exit 3"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let (output, status) = run_and_capture(&command).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("out\n"));
+        assert!(output.contains("err\n"));
+        assert_eq!(status.code(), Some(3));
+    }
+}
@@ -1,14 +1,17 @@
 //! Module responsible for searching through the file system looking for files of interest.
 //!
-//! Files of interest are either Limits.toml files, or files matching the glob patterns registered
-//! for the different [Kind](../settings/struct.Kind.html)s or warnings.
+//! Files of interest are either limits files (Limits.toml by default), or files matching the glob
+//! patterns registered for the different [Kind](../settings/struct.Kind.html)s or warnings. Log
+//! files may be searched for in other places than the limits files, and inside archives.
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 use crossbeam_channel::{bounded, Receiver, Sender};
-use globset::GlobSet;
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use ignore::DirEntry;
+use log::warn;
 
+use crate::archives;
 use crate::settings::Kind;
 use std::io;
 use std::sync::Arc;
@@ -20,8 +23,12 @@ use std::sync::Arc;
 pub struct LogFile(PathBuf, Vec<Kind>);
 
 impl LogFile {
+    pub(crate) fn new(path: PathBuf, kinds: Vec<Kind>) -> Self {
+        LogFile(path, kinds)
+    }
+
     pub(crate) fn path(&self) -> &Path {
-        &self.0.as_path()
+        self.0.as_path()
     }
 
     pub(crate) fn kinds(&self) -> &[Kind] {
@@ -29,6 +36,63 @@ impl LogFile {
     }
 }
 
+#[derive(Debug)]
+/// The glob patterns of the log files of a [Kind](../settings/struct.Kind.html). A pattern like
+/// `**/*.zip!**/build.log` matches the entries inside the archives matching the part before the
+/// `!`, and the other patterns match files on the file system.
+pub(crate) struct LogGlobs {
+    files: GlobSet,
+    archives: GlobSet,
+    entries: GlobSet,
+}
+
+impl LogGlobs {
+    pub(crate) fn new(patterns: &[String]) -> Result<Self, globset::Error> {
+        let mut files = GlobSetBuilder::new();
+        let mut archives = GlobSetBuilder::new();
+        let mut entries = GlobSetBuilder::new();
+        for pattern in patterns {
+            match split_archive_pattern(pattern) {
+                Some((archive, entry)) => {
+                    archives.add(Glob::new(archive)?);
+                    let entry_pattern = archives::entry_path(Path::new(archive), entry);
+                    entries.add(Glob::new(&entry_pattern.to_string_lossy())?);
+                }
+                None => {
+                    files.add(Glob::new(pattern)?);
+                }
+            }
+        }
+        Ok(LogGlobs {
+            files: files.build()?,
+            archives: archives.build()?,
+            entries: entries.build()?,
+        })
+    }
+
+    /// Does the file on the file system at `path` match?
+    pub(crate) fn is_match<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.files.is_match(path)
+    }
+
+    fn is_archive_match(&self, path: &Path) -> bool {
+        self.archives.is_match(path)
+    }
+
+    fn is_entry_match(&self, entry_path: &Path) -> bool {
+        self.entries.is_match(entry_path)
+    }
+}
+
+/// Split a `pattern` like `**/*.zip!**/build.log` into the pattern of the archive, and the pattern
+/// of the entries inside of it. Returns None unless the pattern matches entries inside archives.
+fn split_archive_pattern(pattern: &str) -> Option<(&str, &str)> {
+    pattern
+        .match_indices('!')
+        .map(|(i, _)| (&pattern[..i], pattern[i + 1..].trim_start_matches('/')))
+        .find(|(archive, _entry)| archives::is_archive(Path::new(archive)))
+}
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 /// A partial result of the file search. Signal having found either a
 /// [LimitsFile](struct.LimitsFile.html) or a relevant [log file](struct.LogFile.html).
@@ -40,6 +104,11 @@ pub(crate) enum FileData {
 pub(crate) trait FileSearcher {
     fn normalize_path(path: &Path) -> Result<PathBuf, io::Error>;
     fn traverse<F: Fn(&Path) + Send + Clone + 'static>(start: &Path, callback: F);
+
+    /// The paths of the files inside the `archive`, along with their sizes once extracted.
+    fn archive_entries(archive: &Path) -> Result<Vec<(String, u64)>, io::Error> {
+        archives::list_entries(archive)
+    }
 }
 
 pub(crate) struct IgnoreWalker;
@@ -58,7 +127,7 @@ impl FileSearcher for IgnoreWalker {
             let callback = callback.clone();
             Box::new(move |result| {
                 if let Some(entry) = is_file(result) {
-                    callback(&entry.path());
+                    callback(entry.path());
                 };
                 ignore::WalkState::Continue
             })
@@ -66,20 +135,42 @@ impl FileSearcher for IgnoreWalker {
     }
 }
 
-/// Starts the threads which searches the `start_dir` for files. Uses `types` to know what
-/// [Kind](struct.Kind.html)s of warnings we should look for in the files.
+/// Starts the threads which searches the `start_dir` for files. Files named `limits_file_name` are
+/// limits files, unless it is None, and `types` is used to know what [Kind](struct.Kind.html)s of
+/// warnings we should look for in the other files. If there are `log_roots`, those are searched for
+/// log files instead of the `start_dir`.
 pub(crate) fn construct_file_searcher<F: FileSearcher>(
     start_dir: &Path,
-    types: HashMap<Kind, GlobSet>,
+    limits_file_name: Option<&str>,
+    log_roots: &[PathBuf],
+    types: HashMap<Kind, LogGlobs>,
 ) -> Receiver<FileData> {
     let (tx, rx) = bounded(128);
     let start_dir = start_dir.to_path_buf();
+    let limits_file_name = limits_file_name.map(str::to_owned);
+    let log_roots = log_roots.to_vec();
 
     std::thread::spawn(move || {
         let types = Arc::new(types);
+        if log_roots.is_empty() {
+            F::traverse(&start_dir, move |entry| {
+                process_file::<F>(&tx, entry, limits_file_name.as_deref(), &types);
+            });
+            return;
+        }
+
+        let no_types = Arc::new(HashMap::new());
+        let limits_tx = tx.clone();
         F::traverse(&start_dir, move |entry| {
-            process_file::<F>(&tx, entry, &types);
+            process_file::<F>(&limits_tx, entry, limits_file_name.as_deref(), &no_types);
         });
+        for log_root in log_roots {
+            let tx = tx.clone();
+            let types = types.clone();
+            F::traverse(&log_root, move |entry| {
+                process_file::<F>(&tx, entry, None, &types);
+            });
+        }
     });
     rx
 }
@@ -95,8 +186,13 @@ fn is_file(entry: Result<DirEntry, ignore::Error>) -> Option<DirEntry> {
 }
 
 /// Process the `entry` and reply on the `tx` channel if this is an entry of interest.
-fn process_file<F: FileSearcher>(tx: &Sender<FileData>, entry: &Path, types: &HashMap<Kind, GlobSet>) {
-    if entry.ends_with("Limits.toml") {
+fn process_file<F: FileSearcher>(
+    tx: &Sender<FileData>,
+    entry: &Path,
+    limits_file_name: Option<&str>,
+    types: &HashMap<Kind, LogGlobs>,
+) {
+    if limits_file_name.is_some_and(|name| entry.ends_with(name)) {
         tx.send(FileData::LimitsFile(
             F::normalize_path(entry).expect("Could not normalize"),
         ))
@@ -104,23 +200,58 @@ fn process_file<F: FileSearcher>(tx: &Sender<FileData>, entry: &Path, types: &Ha
     } else {
         let file_ts: Vec<Kind> = types
             .iter()
-            .filter(|(_ft, globs)| globs.is_match(&entry))
+            .filter(|(_ft, globs)| globs.is_match(entry))
             .map(|(ft, _glob)| ft.clone())
             .collect();
 
         if !file_ts.is_empty() {
-            let abs_path = F::normalize_path(&entry).expect("Could not normalize");
+            let abs_path = F::normalize_path(entry).expect("Could not normalize");
             tx.send(FileData::LogFile(LogFile(abs_path, file_ts)))
                 .expect("Could not send FileData::LogFile");
         }
+        if types.values().any(|globs| globs.is_archive_match(entry)) {
+            process_archive::<F>(tx, entry, types);
+        }
+    }
+}
+
+/// Process the entries of the `archive` and reply on the `tx` channel for each entry of interest.
+fn process_archive<F: FileSearcher>(
+    tx: &Sender<FileData>,
+    archive: &Path,
+    types: &HashMap<Kind, LogGlobs>,
+) {
+    let entries = match F::archive_entries(archive) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Could not read archive `{}`. Reason: `{}`", archive.display(), e);
+            return;
+        }
+    };
+    let abs_archive = F::normalize_path(archive).expect("Could not normalize");
+    for (entry, size) in entries {
+        let entry_ts: Vec<Kind> = types
+            .iter()
+            .filter(|(_ft, globs)| globs.is_entry_match(&archives::entry_path(archive, &entry)))
+            .map(|(ft, _glob)| ft.clone())
+            .collect();
+
+        if !entry_ts.is_empty() {
+            let abs_path = archives::entry_path(&abs_archive, &entry);
+            // Empty log files can not tell us anything, nor count as the log files of their kinds
+            if size == 0 {
+                warn!("Log file `{}` is empty", abs_path.display());
+                continue;
+            }
+            tx.send(FileData::LogFile(LogFile(abs_path, entry_ts)))
+                .expect("Could not send FileData::LogFile");
+        }
     }
 }
 
 
 #[cfg(test)]
 mod test {
-    use globset::{Glob, GlobSetBuilder};
-
     use crate::utils::SearchableArena;
 
     use super::*;
@@ -163,9 +294,14 @@ mod test {
         let mut arena = SearchableArena::new();
         let mut interesting_types = HashMap::new();
         let gcc_kind = Kind::new(arena.insert("gcc".to_owned()));
-        let c_globber = GlobSetBuilder::new().add(Glob::new("*.c").expect("Glob")).build().expect("GlobSet");
+        let c_globber = LogGlobs::new(&["*.c".to_owned()]).expect("LogGlobs");
         interesting_types.insert(gcc_kind.clone(), c_globber);
-        let rx = construct_file_searcher::<DummyFileSearcher>(Path::new("somewhere"), interesting_types);
+        let rx = construct_file_searcher::<DummyFileSearcher>(
+            Path::new("somewhere"),
+            Some("Limits.toml"),
+            &[],
+            interesting_types,
+        );
 
         assert_eq_sorted!(
             vec![
@@ -174,4 +310,85 @@ mod test {
             ],
             rx.into_iter().collect::<Vec<_>>());
     }
+
+    #[test]
+    fn log_roots_are_searched_for_log_files_only() {
+        struct DummyFileSearcher;
+
+        impl FileSearcher for DummyFileSearcher {
+            fn normalize_path(path: &Path) -> Result<PathBuf, io::Error> {
+                Ok(path.to_path_buf())
+            }
+
+            fn traverse<F: Fn(&Path)>(start: &Path, callback: F) {
+                callback(&start.join("Limits.toml"));
+                callback(&start.join("main.c"));
+            }
+        }
+
+        let mut arena = SearchableArena::new();
+        let mut interesting_types = HashMap::new();
+        let gcc_kind = Kind::new(arena.insert("gcc".to_owned()));
+        let c_globber = LogGlobs::new(&["*.c".to_owned()]).expect("LogGlobs");
+        interesting_types.insert(gcc_kind.clone(), c_globber);
+        let rx = construct_file_searcher::<DummyFileSearcher>(
+            Path::new("/src"),
+            Some("Limits.toml"),
+            &[PathBuf::from("/logs")],
+            interesting_types,
+        );
+
+        assert_eq_sorted!(
+            vec![
+                FileData::LimitsFile(PathBuf::from("/src/Limits.toml")),
+                FileData::LogFile(LogFile(PathBuf::from("/logs/main.c"), vec![gcc_kind])),
+            ],
+            rx.into_iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn archive_entries_are_log_files_if_they_match() {
+        struct DummyFileSearcher;
+
+        impl FileSearcher for DummyFileSearcher {
+            fn normalize_path(path: &Path) -> Result<PathBuf, io::Error> {
+                Ok(path.to_path_buf())
+            }
+
+            fn traverse<F: Fn(&Path)>(_start: &Path, callback: F) {
+                callback(Path::new("/src/artifacts/job.zip"));
+                callback(Path::new("/src/artifacts/other-job.zip"));
+                callback(Path::new("/src/other.zip"));
+            }
+
+            fn archive_entries(archive: &Path) -> Result<Vec<(String, u64)>, io::Error> {
+                // The build log of the other job is empty
+                let build_log_size = if archive.ends_with("job.zip") { 100 } else { 0 };
+                Ok(vec![
+                    ("logs/build.log".to_owned(), build_log_size),
+                    ("logs/test.log".to_owned(), 100),
+                ])
+            }
+        }
+
+        let mut arena = SearchableArena::new();
+        let mut interesting_types = HashMap::new();
+        let gcc_kind = Kind::new(arena.insert("gcc".to_owned()));
+        let pattern = "**/artifacts/*.zip!**/build.log".to_owned();
+        let globs = LogGlobs::new(&[pattern]).expect("LogGlobs");
+        interesting_types.insert(gcc_kind.clone(), globs);
+        let rx = construct_file_searcher::<DummyFileSearcher>(
+            Path::new("/src"),
+            Some("Limits.toml"),
+            &[],
+            interesting_types,
+        );
+
+        assert_eq!(
+            vec![FileData::LogFile(LogFile(
+                PathBuf::from("/src/artifacts/job.zip!/logs/build.log"),
+                vec![gcc_kind]
+            ))],
+            rx.into_iter().collect::<Vec<_>>());
+    }
 }
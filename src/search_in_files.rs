@@ -1,38 +1,153 @@
 //! Module responsible for searching inside files, looking for warnings and matching them against
 //! the identified limits.
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
 use std::num::NonZeroUsize;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 use crossbeam_channel::Receiver;
+use id_arena::Id;
 use log::{debug, error, trace};
-use regex::Regex;
-
-use crate::limits::{Category, LimitsEntry};
+use regex::{CaptureLocations, Regex};
+use crate::archives;
+use crate::compression;
+use crate::limits::{Category, LimitsEntry, Severity};
+use crate::prefilter::LiteralPrefilter;
 use crate::search_for_files::LogFile;
-use crate::settings::{Kind, Settings};
+use crate::settings::{Encoding, Kind, RelevantRegexes, SearchPattern, Settings, StaleCheck};
 use crate::utils::SearchableArena;
-use crate::warnings::{CountsTowardsLimit, Description};
+use crate::warnings::{CountsTowardsLimit, Description, Occurrence};
 
 /// The LogSearchResult contains the information about what we found in a
 /// [log file](struct.LogFile.html). Because the searches happen in parallel, each LogSearchResult
 /// has its own [string arena](struct.SearchableArena.html) which must later be merged together
 /// in order to get sensible results. The search results maps all matches warnings to the
-/// corresponding [LimitsEntry](struct.LimitsEntry.html).
+/// corresponding [LimitsEntry](struct.LimitsEntry.html). If the log file lacks all of the
+/// completion markers of the [Kind](../settings/struct.Kind.html), it is reported as incomplete.
 pub(crate) struct LogSearchResults {
     pub(crate) string_arena: SearchableArena,
     pub(crate) warnings: HashMap<LimitsEntry, HashSet<CountsTowardsLimit>>,
+    pub(crate) incomplete: Option<(Kind, PathBuf)>,
+    pub(crate) stale: Vec<Stale>,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+/// A stale finding from searching a log file. Either the log file is older than a limits file or
+/// culprit it reports on, or a culprit it reports on does not exist.
+pub(crate) enum Stale {
+    Log { log_file: PathBuf, newer: PathBuf },
+    Culprit { log_file: PathBuf, culprit: PathBuf },
+}
+
+impl Display for Stale {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Stale::Log { log_file, newer } => write!(
+                f,
+                "Log file `{}` is older than `{}`",
+                log_file.display(),
+                newer.display()
+            ),
+            Stale::Culprit { log_file, culprit } => write!(
+                f,
+                "Culprit `{}` in log file `{}` does not exist",
+                culprit.display(),
+                log_file.display()
+            ),
+        }
+    }
+}
+
+/// What is needed to tell whether the search results from a log file are stale. Relative culprits
+/// are resolved against the `start_dir`.
+struct StaleChecks {
+    stale_logs: StaleCheck,
+    missing_culprits: StaleCheck,
+    start_dir: PathBuf,
+    newest_limits_file: Option<(SystemTime, PathBuf)>,
+}
+
+impl StaleChecks {
+    fn new(settings: &Settings, start_dir: &Path, limit_files: &HashSet<PathBuf>) -> Self {
+        let newest_limits_file = limit_files
+            .iter()
+            .filter_map(|file| modified(file).map(|time| (time, file.clone())))
+            .max();
+        StaleChecks {
+            stale_logs: settings.stale_logs(),
+            missing_culprits: settings.missing_culprits(),
+            start_dir: start_dir.to_path_buf(),
+            newest_limits_file: newest_limits_file,
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.stale_logs != StaleCheck::Ignore || self.missing_culprits != StaleCheck::Ignore
+    }
+}
+
+/// When the file at `path` was last modified. Entries in archives were modified with the archive.
+fn modified(path: &Path) -> Option<SystemTime> {
+    let path = archives::split_entry_path(path).map_or(path, |(archive, _entry)| archive);
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
 }
 
 pub(crate) trait FileReader {
-    fn read_file_to_string(path: &Path) -> std::io::Result<String>;
+    fn read_file(path: &Path) -> std::io::Result<Vec<u8>>;
+
+    /// Read the files at the paths `entries` inside the `archive`, in a single pass over it, and
+    /// call `found` with the path and contents of each of them. Entries not in the archive are
+    /// skipped.
+    fn read_archive_entries(
+        archive: &Path,
+        entries: &HashSet<&str>,
+        found: &mut dyn FnMut(&str, std::io::Result<Vec<u8>>),
+    ) -> std::io::Result<()>;
+
+    /// Open the file at `path` to be searched piece by piece, if it is larger than `threshold`
+    /// bytes, or may be once decompressed. Returns None if the file should be read whole.
+    fn open_large_file(_path: &Path, _threshold: u64) -> std::io::Result<Option<LargeFile>> {
+        Ok(None)
+    }
 }
 
+/// A large log file, read piece by piece.
+pub(crate) type LargeFile = Box<dyn BufRead + Send>;
+
+/// A log piped to stdin or captured from a command, rather than read from a file, along with its
+/// contents.
+pub(crate) type PipedLog = (LogFile, Vec<u8>);
+
 pub(crate) struct FileSystemReader;
 
 impl FileReader for FileSystemReader {
-    fn read_file_to_string(path: &Path) -> std::io::Result<String> {
-        std::fs::read_to_string(path)
+    fn read_file(path: &Path) -> std::io::Result<Vec<u8>> {
+        compression::decompress(std::fs::read(path)?)
+    }
+
+    fn read_archive_entries(
+        archive: &Path,
+        entries: &HashSet<&str>,
+        found: &mut dyn FnMut(&str, std::io::Result<Vec<u8>>),
+    ) -> std::io::Result<()> {
+        archives::read_entries(archive, entries, |entry, bytes| {
+            found(entry, compression::decompress(bytes))
+        })
+    }
+
+    fn open_large_file(path: &Path, threshold: u64) -> std::io::Result<Option<LargeFile>> {
+        let mut file = BufReader::new(File::open(path)?);
+        // The size on disk says nothing about the size of a compressed log file once decompressed
+        let is_small = std::fs::metadata(path)?.len() <= threshold;
+        if is_small && !compression::is_decompressed_while_reading(file.fill_buf()?) {
+            return Ok(None);
+        }
+        compression::decompressing_reader(file).map(Some)
     }
 }
 
@@ -41,42 +156,128 @@ impl FileReader for FileSystemReader {
 /// with a [LimitsFile](../limits/struct.Limits.html).
 pub(crate) fn search_files<R: FileReader>(
     settings: &Settings,
+    start_dir: &Path,
     limit_files: &HashSet<PathBuf>,
     log_files: Vec<LogFile>,
+    piped_logs: Vec<PipedLog>,
 ) -> Receiver<Result<LogSearchResults, (LogFile, std::io::Error)>> {
     use rayon::iter::ParallelIterator;
     use rayon::iter::IntoParallelIterator;
     let (tx, rx) = crossbeam_channel::bounded(128);
 
     let regexes_to_use = settings.kinds_and_regex();
+    let streaming_threshold = settings.streaming_threshold();
+    let stale_checks = StaleChecks::new(settings, start_dir, limit_files);
     let limit_files = limit_files.clone();
 
     std::thread::spawn(move || {
-        // Parse all log files in parallel
-        log_files.into_par_iter().for_each(|lf| {
-            match R::read_file_to_string(lf.path()) {
-                Ok(loaded_file) => {
-                    // TODO: figure out a way to cleanly skip reading the file if we're skipping
-                    // all of its kinds.
-                    for kind in lf.kinds() {
-                        if let Some(regex) = regexes_to_use.get(kind) {
-                            let result = search_contents_with_regex(
-                                &limit_files,
-                                kind,
-                                &loaded_file,
-                                regex,
-                            );
-                            tx.send(Ok(result)).expect("Could not send() result");
+        let report = |lf: &LogFile, mut result: LogSearchResults| {
+            if stale_checks.is_enabled() {
+                check_staleness(&mut result, lf.path(), &stale_checks);
+            }
+            tx.send(Ok(result)).expect("Could not send() result");
+        };
+        let search_log = |lf: &LogFile,
+                          groups: &[Vec<(&Kind, &SearchPattern)>],
+                          loaded_file: &[u8]| {
+            // Each group of kinds may decode the log file differently
+            for group in groups {
+                let pattern = group[0].1;
+                match pattern.encoding.decode(loaded_file, pattern.lossy_decoding) {
+                    Ok(contents) => {
+                        for result in search_contents(&limit_files, lf.path(), &contents, group) {
+                            report(lf, result);
                         }
                     }
-                },
-                Err(e) => {
-                    error!("Could not read log file: {}, {}", lf.path().display(), e);
-                    tx.send(Err((lf, e)))
-                        .expect("Could not send() logfile io error");
-                },
-            }
-        });
+                    Err(e) => {
+                        error!("Could not decode log file: {}, {}", lf.path().display(), e);
+                        let e = std::io::Error::new(std::io::ErrorKind::InvalidData, e);
+                        let kinds = group.iter().map(|(kind, _)| (*kind).clone()).collect();
+                        let undecodable = LogFile::new(lf.path().to_path_buf(), kinds);
+                        tx.send(Err((undecodable, e)))
+                            .expect("Could not send() logfile decoding error");
+                    }
+                }
+            }
+        };
+        let fail = |lf: LogFile, e: std::io::Error| {
+            error!("Could not read log file: {}, {}", lf.path().display(), e);
+            tx.send(Err((lf, e)))
+                .expect("Could not send() logfile io error");
+        };
+        // Piped logs are already read, so search them like any log file
+        for (lf, contents) in &piped_logs {
+            search_log(lf, &kinds_by_reading(lf, &regexes_to_use), contents);
+        }
+        // The entries of an archive are all read in a single pass over the archive
+        let mut files = Vec::new();
+        let mut archive_entries: HashMap<PathBuf, Vec<LogFile>> = HashMap::new();
+        for lf in log_files {
+            match archives::split_entry_path(lf.path()).map(|(archive, _)| archive.to_path_buf()) {
+                Some(archive) => archive_entries.entry(archive).or_default().push(lf),
+                None => files.push(lf),
+            }
+        }
+        let search_archive = |(archive, entries): (PathBuf, Vec<LogFile>)| {
+            let mut by_entry: HashMap<&str, &LogFile> = entries
+                .iter()
+                .filter_map(|lf| Some((archives::split_entry_path(lf.path())?.1, lf)))
+                .collect();
+            let names: HashSet<&str> = by_entry.keys().cloned().collect();
+            let read = R::read_archive_entries(&archive, &names, &mut |entry, loaded_file| {
+                // A tar archive may have several entries by the same name
+                let lf = match by_entry.remove(entry) {
+                    Some(lf) => lf,
+                    None => return,
+                };
+                match loaded_file {
+                    Ok(loaded_file) => {
+                        search_log(lf, &kinds_by_reading(lf, &regexes_to_use), &loaded_file)
+                    }
+                    Err(e) => fail(LogFile::new(lf.path().to_path_buf(), lf.kinds().to_vec()), e),
+                }
+            });
+            // Report the entries which could not be read
+            for lf in by_entry.values() {
+                let e = match &read {
+                    Ok(()) => std::io::Error::new(std::io::ErrorKind::NotFound, "No such entry"),
+                    Err(e) => std::io::Error::new(e.kind(), e.to_string()),
+                };
+                fail(LogFile::new(lf.path().to_path_buf(), lf.kinds().to_vec()), e);
+            }
+        };
+        // Parse all log files in parallel
+        let search_file = |lf: LogFile| {
+            let groups = kinds_by_reading(&lf, &regexes_to_use);
+            // Large log files are searched piece by piece, to not hold all of them in memory
+            let streamed = match R::open_large_file(lf.path(), streaming_threshold) {
+                Ok(Some(large_file)) => {
+                    search_large_file(&limit_files, lf.path(), large_file, &groups)
+                }
+                Ok(None) => Ok(None),
+                Err(e) => Err(e),
+            };
+            let read_whole = match streamed {
+                Ok(Some(results)) => {
+                    for result in results {
+                        report(&lf, result);
+                    }
+                    return;
+                }
+                Ok(None) => R::read_file(lf.path()),
+                Err(e) => Err(e),
+            };
+            match read_whole {
+                // TODO: figure out a way to cleanly skip reading the file if we're skipping
+                // all of its kinds.
+                Ok(loaded_file) => search_log(&lf, &groups, &loaded_file),
+                Err(e) => fail(lf, e),
+            }
+        };
+        rayon::join(
+            || files.into_par_iter().for_each(search_file),
+            || archive_entries.into_par_iter().for_each(search_archive),
+        );
     });
     rx
 }
@@ -85,78 +286,517 @@ pub(crate) fn search_files<R: FileReader>(
 // but some build system might do the equivalent of "make all" > big_log.txt,
 // or it might be the console log from Jenkins
 
-/// Search through the `file_contents` using the specified `regex`. Match any findings towards the
-/// appropriate [LimitsEntry](../limits/struct.LimitsEntry.html) and return the
-/// [search results](struct.LogSearchResults.html).
-fn search_contents_with_regex(
+/// The kinds of the log file `lf` to search for, and their patterns. Kinds which read the log file
+/// alike are grouped together, so they can all be searched for at once.
+fn kinds_by_reading<'a>(
+    lf: &'a LogFile,
+    regexes: &'a RelevantRegexes,
+) -> Vec<Vec<(&'a Kind, &'a SearchPattern)>> {
+    let mut result: Vec<Vec<(&Kind, &SearchPattern)>> = Vec::new();
+    for kind in lf.kinds() {
+        let pattern = match regexes.get(kind) {
+            Some(pattern) => pattern,
+            None => continue,
+        };
+        match result.iter_mut().find(|group| group[0].1.reads_logs_like(pattern)) {
+            Some(group) => group.push((kind, pattern)),
+            None => result.push(vec![(kind, pattern)]),
+        }
+    }
+    result
+}
+
+/// Search through the decoded `file_contents` of `log_file` for warnings of each of the kinds in
+/// the `group`, using the regex of their pattern. Match any findings towards the appropriate
+/// [LimitsEntry](../limits/struct.LimitsEntry.html) and return the
+/// [search results](struct.LogSearchResults.html) of each kind.
+fn search_contents(
     limits: &HashSet<PathBuf>,
-    kind: &Kind,
+    log_file: &Path,
     file_contents: &str,
-    regex: &Regex,
-) -> LogSearchResults {
-    let mut result = LogSearchResults {
-        string_arena: SearchableArena::new(),
-        warnings: HashMap::new(),
-    };
-    // Let's cache the results we get from the calls to `find_limits_for`, in case we get multiple
-    // warnings from the same file.
-    let mut limits_cache: HashMap<PathBuf, Option<&PathBuf>> = HashMap::new();
-
-    for matching in regex.captures_iter(file_contents) {
-        // What file is the culprit? TODO: We don't have any decent normalize() function yet..
-        let culprit_file = matching
-            .name("file")
-            .map(|m| PathBuf::from(m.as_str().replace("\\", "/")))
-            .unwrap();
-
-        // Try to identify the warning using line, column, category and description
-        let line: Option<NonZeroUsize> = matching.name("line").map(|m| {
-            m.as_str()
-                .parse()
-                .unwrap_or_else(|e| panic!("Capture for `line` was not a non zero number: `{}`", e))
-        });
-        let column: Option<NonZeroUsize> = matching.name("column").map(|m| {
-            m.as_str().parse().unwrap_or_else(|e| {
-                panic!("Capture for `column` was not a non zero number: `{}`", e)
-            })
-        });
-        let cat_match = matching.name("category").map(|m| m.as_str());
-        let desc_match = matching.name("description").map(|m| m.as_str());
+    group: &[(&Kind, &SearchPattern)],
+) -> Vec<LogSearchResults> {
+    let file_contents = group[0].1.preprocessing.apply(file_contents);
+    let mut search = MultiKindSearch::new(limits, log_file, group);
+    search.search_whole_text(&file_contents);
+    search.finish()
+}
+
+/// How much of a large log file is read at a time, before reading on to the end of the line.
+const PIECE_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Search the `large_file` read from `log_file` piece by piece, for warnings of each of the kinds
+/// in the `groups` of the log file. Returns None if the encoding of the log file does not allow it
+/// to be split into lines without decoding it first, so it must be read whole.
+fn search_large_file(
+    limits: &HashSet<PathBuf>,
+    log_file: &Path,
+    mut large_file: LargeFile,
+    groups: &[Vec<(&Kind, &SearchPattern)>],
+) -> std::io::Result<Option<Vec<LogSearchResults>>> {
+    let start = large_file.fill_buf()?;
+    if !groups
+        .iter()
+        .flatten()
+        .all(|(_kind, pattern)| pattern.encoding.is_splittable_at_newlines(start))
+    {
+        return Ok(None);
+    }
+    let mut searches: Vec<_> = groups
+        .iter()
+        .map(|group| MultiKindSearch::new(limits, log_file, group))
+        .collect();
+    let mut piece = Vec::new();
+    loop {
+        piece.clear();
+        large_file.by_ref().take(PIECE_SIZE).read_to_end(&mut piece)?;
+        large_file.read_until(b'\n', &mut piece)?;
+        let is_last = large_file.fill_buf()?.is_empty();
+        for search in &mut searches {
+            search.search_piece(&piece, is_last)?;
+        }
+        if is_last {
+            break;
+        }
+    }
+    Ok(Some(searches.into_iter().flat_map(MultiKindSearch::finish).collect()))
+}
+
+/// A search through a log file for warnings of several kinds at once, which read the log file
+/// alike. A [prefilter](../prefilter/struct.LiteralPrefilter.html) finds which kinds may have
+/// warnings in the text, in a single pass over it, so that the regexes of the other kinds need not
+/// search it.
+///
+/// A large log file is searched piece by piece. The prefilter then also finds the regions of each
+/// piece where the warnings of each kind may be, and the regex of each kind only searches those
+/// regions. The text after the warnings found so far, and within the last `max_match_lines` lines
+/// of a piece, is `kept` and searched again along with the next piece, so that warnings spanning
+/// several lines are found even when split between pieces.
+struct MultiKindSearch<'a> {
+    searches: Vec<ContentSearch<'a>>,
+    // Where in the log file the last warning of each kind ends
+    resume_at: Vec<usize>,
+    prefilter: Option<LiteralPrefilter>,
+    max_match_lines: usize,
+    // The encoding of the log file, once its first piece has told whether it has a byte order mark
+    encoding: Option<Encoding>,
+    kept: String,
+    kept_offset: usize,
+}
+
+impl<'a> MultiKindSearch<'a> {
+    fn new(
+        limits: &'a HashSet<PathBuf>,
+        log_file: &'a Path,
+        group: &[(&'a Kind, &'a SearchPattern)],
+    ) -> Self {
+        let regexes: Vec<_> = group
+            .iter()
+            .map(|(_, pattern)| (&pattern.regex, pattern.max_match_lines))
+            .collect();
+        MultiKindSearch {
+            searches: group
+                .iter()
+                .map(|(kind, pattern)| ContentSearch::new(limits, kind, log_file, pattern))
+                .collect(),
+            resume_at: vec![0; group.len()],
+            prefilter: LiteralPrefilter::new(&regexes),
+            max_match_lines: group.iter().map(|(_, p)| p.max_match_lines).max().unwrap_or(1),
+            encoding: None,
+            kept: String::new(),
+            kept_offset: 0,
+        }
+    }
 
-        let limits_file = limits_cache.entry(culprit_file.clone())
-            .or_insert_with(|| find_limits_for(limits, culprit_file.as_path()))
-            .as_deref();
+    /// Search the whole `text` of a log file with the regex of each kind that may have warnings in
+    /// it.
+    fn search_whole_text(&mut self, text: &str) {
+        let found = match &self.prefilter {
+            Some(prefilter) => prefilter.kinds_found(text),
+            None => vec![true; self.searches.len()],
+        };
+        for (search, found) in self.searches.iter_mut().zip(found) {
+            search.check_complete(text);
+            if found {
+                search.search(text, 0, 0, None);
+            }
+        }
+    }
 
-        let category = match cat_match {
-            Some(cat_str) => Category::new(result.string_arena.get_or_insert(cat_str)),
-            None => Category::none(),
+    /// Decode, preprocess and search the next `piece` of a large log file.
+    fn search_piece(&mut self, piece: &[u8], is_last: bool) -> std::io::Result<()> {
+        let pattern = self.searches[0].pattern;
+        // Only the first piece may start with a byte order mark
+        let (encoding, piece) = match self.encoding {
+            Some(encoding) => (encoding, piece),
+            None => pattern.encoding.after_byte_order_mark(piece),
         };
-        let description = match desc_match {
-            Some(desc_str) => Description::new(result.string_arena.get_or_insert(desc_str)),
-            None => Description::none(),
+        self.encoding = Some(encoding);
+        let decoded = encoding
+            .decode_exactly(piece, pattern.lossy_decoding)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        self.search_text(&pattern.preprocessing.apply(&decoded), is_last);
+        Ok(())
+    }
+
+    /// Search the regions of the next `text` of a large log file, leaving any warnings starting
+    /// within its last lines for the next text, unless it is the last one.
+    fn search_text(&mut self, text: &str, is_last: bool) {
+        // Only copy the text if there is text kept from the previous one
+        let text = if self.kept.is_empty() {
+            Cow::Borrowed(text)
+        } else {
+            let mut kept = std::mem::take(&mut self.kept);
+            kept.push_str(text);
+            Cow::Owned(kept)
         };
-        let category_to_match = if limits_file.is_some() {
-            category.clone()
+        let until = if is_last {
+            None
         } else {
-            Category::none()
-        };
-        let limits_entry = LimitsEntry::new(limits_file, kind.clone(), category_to_match);
-        let warning = CountsTowardsLimit::new(
-            culprit_file,
-            line,
-            column,
-            kind.clone(),
-            category,
-            description,
-        );
+            Some(start_of_last_lines(&text, self.max_match_lines))
+        };
 
-        result
-            .warnings
-            .entry(limits_entry)
-            .or_insert_with(HashSet::new)
-            .insert(warning);
+        let regions = match &self.prefilter {
+            Some(prefilter) => prefilter.regions(&text),
+            None => vec![None; self.searches.len()],
+        };
+        let searches = self.searches.iter_mut().zip(&mut self.resume_at);
+        for ((search, resume_at), regions) in searches.zip(regions) {
+            search.check_complete(&text);
+            let whole_text = 0..text.len();
+            let regions = regions.unwrap_or_else(|| vec![whole_text]);
+            for region in regions {
+                let from = region.start.max(resume_at.saturating_sub(self.kept_offset));
+                if from >= region.end || until.is_some_and(|until| from >= until) {
+                    continue;
+                }
+                // The regex sees the text before `from`, so that `^` only matches at line starts
+                let found = search.search(&text[..region.end], from, self.kept_offset, until);
+                if let Some(end) = found {
+                    *resume_at = self.kept_offset + end;
+                }
+            }
+        }
+
+        let keep_from = until.unwrap_or(text.len());
+        self.kept = match text {
+            Cow::Owned(mut kept) => {
+                kept.drain(..keep_from);
+                kept
+            }
+            Cow::Borrowed(text) => text[keep_from..].to_owned(),
+        };
+        self.kept_offset += keep_from;
+    }
+
+    fn finish(self) -> Vec<LogSearchResults> {
+        self.searches.into_iter().map(ContentSearch::finish).collect()
+    }
+}
+
+/// Where the last `lines` lines of the `text` start.
+fn start_of_last_lines(text: &str, lines: usize) -> usize {
+    let text = text.strip_suffix('\n').unwrap_or(text);
+    text.rmatch_indices('\n')
+        .nth(lines - 1)
+        .map_or(0, |(i, _newline)| i + 1)
+}
+
+/// A search through the contents of a log file, for warnings of a
+/// [Kind](../settings/struct.Kind.html). The contents may be searched all at once, or
+/// [piece by piece](struct.MultiKindSearch.html).
+struct ContentSearch<'a> {
+    limits: &'a HashSet<PathBuf>,
+    kind: &'a Kind,
+    log_file: &'a Path,
+    pattern: &'a SearchPattern,
+    // Let's cache the results we get from the calls to `find_limits_for`, in case we get multiple
+    // warnings from the same file.
+    limits_cache: HashMap<PathBuf, Option<&'a PathBuf>>,
+    log_file_id: Option<Id<String>>,
+    is_complete: bool,
+    result: LogSearchResults,
+}
+
+impl<'a> ContentSearch<'a> {
+    fn new(
+        limits: &'a HashSet<PathBuf>,
+        kind: &'a Kind,
+        log_file: &'a Path,
+        pattern: &'a SearchPattern,
+    ) -> Self {
+        let mut result = LogSearchResults {
+            string_arena: SearchableArena::new(),
+            warnings: HashMap::new(),
+            incomplete: None,
+            stale: Vec::new(),
+        };
+        // Without deduplication, warnings are told apart by where in the log they were found
+        let log_file_id = if pattern.dedup {
+            None
+        } else {
+            Some(result.string_arena.get_or_insert(&log_file.to_string_lossy()))
+        };
+        ContentSearch {
+            limits: limits,
+            kind: kind,
+            log_file: log_file,
+            pattern: pattern,
+            limits_cache: HashMap::new(),
+            log_file_id: log_file_id,
+            is_complete: pattern.complete_markers.is_empty(),
+            result: result,
+        }
+    }
+
+    /// Look for any of the completion markers in the preprocessed `file_contents`.
+    fn check_complete(&mut self, file_contents: &str) {
+        // A log without any of the completion markers is likely truncated by a crashed build
+        if !self.is_complete {
+            let markers = &self.pattern.complete_markers;
+            self.is_complete = markers.iter().any(|m| m.is_match(file_contents));
+        }
+    }
+
+    /// Search the preprocessed `file_contents`, found at `offset` in the log file, for warnings
+    /// starting at or after `from`. Warnings starting at or after `until` are left to be found by a
+    /// later search. Returns where the last warning found ends, if any was found.
+    fn search(
+        &mut self,
+        file_contents: &str,
+        from: usize,
+        offset: usize,
+        until: Option<usize>,
+    ) -> Option<usize> {
+        let pattern = self.pattern;
+        // Warnings of kinds in a group count towards the limits of the group
+        let limits_kind = pattern.group.as_ref().unwrap_or(self.kind);
+        let limits = self.limits;
+        let limits_cache = &mut self.limits_cache;
+        let result = &mut self.result;
+        let mut searched = None;
+
+        for matching in CapturesFrom::new(&pattern.regex, file_contents, from) {
+            let whole_match = matching.whole();
+            if until.is_some_and(|until| whole_match.start >= until) {
+                break;
+            }
+            searched = Some(whole_match.end);
+            let sev_match = matching.name("severity").map(|m| pattern.severity.normalize(m));
+            if let Some(sev_str) = sev_match {
+                if pattern.severity.should_ignore(sev_str) {
+                    continue;
+                }
+            }
+
+            // What file is the culprit? TODO: We don't have any decent normalize() function yet..
+            let culprit_file = matching
+                .name("file")
+                .map(|m| PathBuf::from(m.replace("\\", "/")))
+                .unwrap();
+
+            // Try to identify the warning using line, column, category and description
+            let line: Option<NonZeroUsize> = matching.name("line").map(|m| {
+                m.parse().unwrap_or_else(|e| {
+                    panic!("Capture for `line` was not a non zero number: `{}`", e)
+                })
+            });
+            let column: Option<NonZeroUsize> = matching.name("column").map(|m| {
+                m.parse().unwrap_or_else(|e| {
+                    panic!("Capture for `column` was not a non zero number: `{}`", e)
+                })
+            });
+            let raw_category = pattern.category.capture(|name| matching.name(name));
+            let cat_match = raw_category
+                .as_ref()
+                .map(|cat_str| pattern.category.normalize(cat_str));
+            let desc_match = matching.name("description");
+
+            let limits_file = limits_cache.entry(culprit_file.clone())
+                .or_insert_with(|| find_limits_for(limits, culprit_file.as_path()))
+                .as_deref();
+
+            let severity = match sev_match {
+                Some(sev_str) => Severity::new(result.string_arena.get_or_insert(sev_str)),
+                None => Severity::none(),
+            };
+            let category = match cat_match {
+                Some(cat_str) => Category::new(result.string_arena.get_or_insert(&cat_str)),
+                None => Category::none(),
+            };
+            let description = match desc_match {
+                Some(desc_str) => Description::new(result.string_arena.get_or_insert(desc_str)),
+                None => Description::none(),
+            };
+            let (severity_to_match, category_to_match) = if limits_file.is_some() {
+                (severity.clone(), category.clone())
+            } else {
+                (Severity::none(), Category::none())
+            };
+            let limits_entry = LimitsEntry::new(limits_file, limits_kind.clone(), category_to_match)
+                .with_severity(severity_to_match);
+            let warning = CountsTowardsLimit::new(
+                culprit_file,
+                line,
+                column,
+                self.kind.clone(),
+                category,
+                description,
+            )
+            .with_severity(severity)
+            .with_identity(pattern.identity);
+            let warning = match self.log_file_id {
+                Some(id) => {
+                    warning.with_occurrence(Occurrence::new(id, offset + whole_match.start))
+                }
+                None => warning,
+            };
+
+            result
+                .warnings
+                .entry(limits_entry)
+                .or_default()
+                .insert(warning);
+        }
+        searched
+    }
+
+    fn finish(mut self) -> LogSearchResults {
+        if !self.is_complete {
+            self.result.incomplete = Some((self.kind.clone(), self.log_file.to_path_buf()));
+        }
+        self.result
+    }
+}
+
+/// The matches of a regex in a text, starting at or after `from`. Like `Regex::captures_iter`, but
+/// the regex sees the text before `from`, so that anchors like `^` and `\b` match there as they do
+/// when searching the whole text.
+struct CapturesFrom<'r, 't> {
+    regex: &'r Regex,
+    text: &'t str,
+    from: usize,
+    last_match_end: Option<usize>,
+}
+
+impl<'r, 't> CapturesFrom<'r, 't> {
+    fn new(regex: &'r Regex, text: &'t str, from: usize) -> Self {
+        CapturesFrom {
+            regex: regex,
+            text: text,
+            from: from,
+            last_match_end: None,
+        }
+    }
+}
+
+impl<'r, 't> Iterator for CapturesFrom<'r, 't> {
+    type Item = CapturedFrom<'r, 't>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.from > self.text.len() {
+                return None;
+            }
+            let mut locations = self.regex.capture_locations();
+            let whole = self.regex.captures_read_at(&mut locations, self.text, self.from)?;
+            if whole.start() == whole.end() {
+                // Step over an empty match, and skip it if right after the previous match
+                let next_char = self.text[whole.end()..].chars().next();
+                self.from = whole.end() + next_char.map_or(1, char::len_utf8);
+                if self.last_match_end == Some(whole.end()) {
+                    continue;
+                }
+            } else {
+                self.from = whole.end();
+            }
+            self.last_match_end = Some(whole.end());
+            return Some(CapturedFrom {
+                regex: self.regex,
+                text: self.text,
+                locations: locations,
+            });
+        }
+    }
+}
+
+/// A match found by [CapturesFrom](struct.CapturesFrom.html).
+struct CapturedFrom<'r, 't> {
+    regex: &'r Regex,
+    text: &'t str,
+    locations: CaptureLocations,
+}
+
+impl<'r, 't> CapturedFrom<'r, 't> {
+    /// Where the whole match is in the text.
+    fn whole(&self) -> Range<usize> {
+        let (start, end) = self.locations.get(0).expect("A match captures itself");
+        start..end
+    }
+
+    /// The text captured by the group with the `name`, if it participated in the match.
+    fn name(&self, name: &str) -> Option<&'t str> {
+        let index = self.regex.capture_names().position(|n| n == Some(name))?;
+        let (start, end) = self.locations.get(index)?;
+        Some(&self.text[start..end])
+    }
+}
+
+/// Compare the `log_file` with the files its search `result` depends on. The log file is stale if
+/// it is older than the newest limits file or culprit, and each culprit that does not exist is
+/// missing. Stale findings are recorded in the result, unless ignored, and the warnings they
+/// concern are removed if they should be dropped.
+fn check_staleness(result: &mut LogSearchResults, log_file: &Path, checks: &StaleChecks) {
+    let culprits: HashSet<PathBuf> = result
+        .warnings
+        .values()
+        .flatten()
+        .map(|w| w.culprit().to_path_buf())
+        .collect();
+    let mut newest = checks.newest_limits_file.clone();
+    let mut missing = HashSet::new();
+    for culprit in culprits {
+        let path = checks.start_dir.join(&culprit);
+        match modified(&path) {
+            Some(time) if newest.as_ref().is_none_or(|(newest, _)| time > *newest) => {
+                newest = Some((time, culprit));
+            }
+            Some(_) => {}
+            None if !path.exists() => {
+                missing.insert(culprit);
+            }
+            None => {}
+        }
+    }
+
+    if checks.missing_culprits != StaleCheck::Ignore && !missing.is_empty() {
+        let mut missing_sorted: Vec<_> = missing.iter().cloned().collect();
+        missing_sorted.sort();
+        result.stale.extend(missing_sorted.into_iter().map(|culprit| Stale::Culprit {
+            log_file: log_file.to_path_buf(),
+            culprit: culprit,
+        }));
+        if checks.missing_culprits == StaleCheck::Drop {
+            for warnings in result.warnings.values_mut() {
+                warnings.retain(|w| !missing.contains(w.culprit()));
+            }
+            result.warnings.retain(|_entry, warnings| !warnings.is_empty());
+        }
+    }
+
+    if checks.stale_logs != StaleCheck::Ignore {
+        if let (Some(log_time), Some((newest_time, newer))) = (modified(log_file), newest) {
+            if newest_time > log_time {
+                result.stale.push(Stale::Log {
+                    log_file: log_file.to_path_buf(),
+                    newer: newer,
+                });
+                if checks.stale_logs == StaleCheck::Drop {
+                    result.warnings.clear();
+                }
+            }
+        }
     }
-    result
 }
 
 /// Every warning originates at a "culprit" file. These files are located under a Limits.toml file
@@ -166,9 +806,9 @@ fn search_contents_with_regex(
 /// IMPORTANT NOTE: When run under Linux, `culprit_file` *must not* include \ -characters, because
 /// of how Rust doesn't treat them as path separators. `build_regex_searcher` does a string replace
 /// operation before calling this function, so it shouldn't be a problem in real world scenarios.
-fn find_limits_for<'limits, 'culprit>(
+fn find_limits_for<'limits>(
     limits: &'limits HashSet<PathBuf>,
-    culprit_file: &'culprit Path,
+    culprit_file: &Path,
 ) -> Option<&'limits PathBuf> {
     for parent_dir in culprit_file.ancestors() {
         // This happens when parent_dir turns into empty string,
@@ -219,4 +859,644 @@ mod test {
             Some(&limits_2)
         );
     }
+
+    #[test]
+    fn search_contents_maps_and_ignores_severities() {
+        let settings_str = r#"
+        [clang]
+        regex = "^(?P<file>[^:]+):(?P<line>\\d+): (?P<severity>[a-z ]+): (?P<description>.+)$"
+        files = ["**/clang.txt"]
+        severity = { map = { "fatal error" = "error" }, ignore = ["note"] }
+        "#;
+        let settings = toml::from_str::<Settings>(settings_str).unwrap();
+        let kind = Kind::new(settings.string_arena.get_id("clang").unwrap());
+        let regexes = settings.kinds_and_regex();
+        let pattern = regexes.get(&kind).unwrap();
+
+        let limits_file = PathBuf::from("src/Limits.toml");
+        let limits: HashSet<PathBuf> = vec![limits_file.clone()].into_iter().collect();
+        let log = "src/a.c:1: warning: unused variable\n\
+                   src/a.c:1: note: declared here\n\
+                   src/a.c:2: fatal error: file not found\n";
+
+        let result =
+            search_contents_with_regex(&limits, &kind, Path::new("clang.txt"), log, pattern);
+        let arena = &result.string_arena;
+        let severities: HashSet<&str> = result
+            .warnings
+            .keys()
+            .map(|entry| {
+                assert_eq!(entry.limits_file.as_ref(), Some(&limits_file));
+                entry.severity.to_str(arena).unwrap()
+            })
+            .collect();
+        let expected: HashSet<&str> = vec!["warning", "error"].into_iter().collect();
+        assert_eq!(severities, expected);
+    }
+
+    #[test]
+    fn warnings_for_missing_culprits_can_be_dropped() {
+        let settings_str = r#"
+        missing_culprits = "drop"
+
+        [kind]
+        regex = "^(?P<file>[^:]+):(?P<line>\\d+): (?P<description>.+)$"
+        files = ["**/log.txt"]
+        "#;
+        let settings = toml::from_str::<Settings>(settings_str).unwrap();
+        let kind = Kind::new(settings.string_arena.get_id("kind").unwrap());
+        let regexes = settings.kinds_and_regex();
+        let pattern = regexes.get(&kind).unwrap();
+        let log_file = Path::new("log.txt");
+        let start_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let log = "src/main.rs:1: exists\nsrc/deleted.rs:1: does not exist\n";
+
+        let checks = StaleChecks::new(&settings, start_dir, &HashSet::new());
+        let mut result = search_contents_with_regex(&HashSet::new(), &kind, log_file, log, pattern);
+        check_staleness(&mut result, log_file, &checks);
+
+        assert_eq!(
+            result.stale,
+            vec![Stale::Culprit {
+                log_file: log_file.to_path_buf(),
+                culprit: PathBuf::from("src/deleted.rs"),
+            }]
+        );
+        let culprits: Vec<_> = result.warnings.values().flatten().map(|w| w.culprit()).collect();
+        assert_eq!(culprits, vec![Path::new("src/main.rs")]);
+    }
+
+    #[test]
+    fn logs_are_preprocessed_before_searching() {
+        let log = "[12:00:01] \x1b[1msrc/a.c:1:\x1b[0m \x1b[35mwarning:\x1b[0m unused `x`\r\n\
+                   [12:00:02] src/a.c:2: warning: unused `y`\r\n\
+                   src/a.c:3: warning: unused `z`\r\n";
+        let without_preprocessing = r#"
+        [kind]
+        regex = "^(?P<file>[^:]+):(?P<line>\\d+): warning: (?P<description>.+)$"
+        files = ["**/log.txt"]
+        "#;
+        // Only the last line is found, with a `\r` ending its description
+        assert_eq!(count_warnings(without_preprocessing, log), 1);
+
+        let with_preprocessing = r#"
+        [kind]
+        regex = "^(?P<file>[^:]+):(?P<line>\\d+): warning: (?P<description>.+)$"
+        files = ["**/log.txt"]
+
+        [kind.preprocess]
+        normalize_line_endings = true
+        strip_ansi = true
+        strip_prefix = "\\[\\d+:\\d+:\\d+\\] "
+        "#;
+        assert_eq!(count_warnings(with_preprocessing, log), 3);
+    }
+
+    fn search_contents_with_regex(
+        limits: &HashSet<PathBuf>,
+        kind: &Kind,
+        log_file: &Path,
+        file_contents: &str,
+        pattern: &SearchPattern,
+    ) -> LogSearchResults {
+        search_contents(limits, log_file, file_contents, &[(kind, pattern)]).pop().unwrap()
+    }
+
+    fn count_warnings(settings_str: &str, log: &str) -> usize {
+        let settings = toml::from_str::<Settings>(settings_str).unwrap();
+        let kind = Kind::new(settings.string_arena.get_id("kind").unwrap());
+        let regexes = settings.kinds_and_regex();
+        let pattern = regexes.get(&kind).unwrap();
+        let result =
+            search_contents_with_regex(&HashSet::new(), &kind, Path::new("log.txt"), log, pattern);
+        result.warnings.values().map(HashSet::len).sum()
+    }
+
+    #[test]
+    fn piped_logs_are_decoded_like_log_files() {
+        let settings_str = r#"
+        [kind]
+        regex = "^(?P<file>[^:]+):(?P<line>\\d+): (?P<description>.+)$"
+        files = ["**/log.txt"]
+        encoding = "latin1"
+        "#;
+        let settings = toml::from_str::<Settings>(settings_str).unwrap();
+        let kind = Kind::new(settings.string_arena.get_id("kind").unwrap());
+        let piped_log = LogFile::new(PathBuf::from("<stdin>"), vec![kind]);
+        // "unused variable `größe`" in Latin-1, which is not valid UTF-8
+        let contents = b"src/a.c:1: unused variable `gr\xf6\xdfe`\n".to_vec();
+
+        let rx = search_files::<FileSystemReader>(
+            &settings,
+            Path::new("."),
+            &HashSet::new(),
+            Vec::new(),
+            vec![(piped_log, contents)],
+        );
+        let results: Vec<_> = rx.into_iter().map(Result::unwrap).collect();
+        assert_eq!(results.len(), 1);
+        let arena = &results[0].string_arena;
+        let descriptions: Vec<_> = results[0]
+            .warnings
+            .values()
+            .flatten()
+            .map(|w| w.display(arena).to_string())
+            .collect();
+        assert_eq!(descriptions.len(), 1);
+        assert!(descriptions[0].contains("unused variable `größe`"));
+    }
+
+    #[test]
+    fn entries_of_an_archive_are_read_in_a_single_pass() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static ARCHIVE_READS: AtomicUsize = AtomicUsize::new(0);
+        struct ArchiveReader;
+
+        impl FileReader for ArchiveReader {
+            fn read_file(path: &Path) -> std::io::Result<Vec<u8>> {
+                panic!("`{}` is not in an archive", path.display())
+            }
+
+            fn read_archive_entries(
+                _archive: &Path,
+                entries: &HashSet<&str>,
+                found: &mut dyn FnMut(&str, std::io::Result<Vec<u8>>),
+            ) -> std::io::Result<()> {
+                ARCHIVE_READS.fetch_add(1, Ordering::SeqCst);
+                for entry in entries.iter().filter(|entry| **entry != "missing.log") {
+                    found(entry, Ok(format!("src/{}.c:1: warning\n", entry).into_bytes()));
+                }
+                Ok(())
+            }
+        }
+
+        let settings_str = r#"
+        [kind]
+        regex = "^(?P<file>[^:]+):(?P<line>\\d+): (?P<description>.+)$"
+        files = ["**/job.zip!**/*.log"]
+        "#;
+        let settings = toml::from_str::<Settings>(settings_str).unwrap();
+        let kind = Kind::new(settings.string_arena.get_id("kind").unwrap());
+        let log_files = ["a.log", "b.log", "missing.log"]
+            .iter()
+            .map(|entry| archives::entry_path(Path::new("job.zip"), entry))
+            .map(|path| LogFile::new(path, vec![kind.clone()]))
+            .collect();
+
+        let rx = search_files::<ArchiveReader>(
+            &settings,
+            Path::new("."),
+            &HashSet::new(),
+            log_files,
+            Vec::new(),
+        );
+        let (found, failed): (Vec<_>, Vec<_>) = rx.into_iter().partition(Result::is_ok);
+        assert_eq!(ARCHIVE_READS.load(Ordering::SeqCst), 1);
+        assert_eq!(found.len(), 2);
+        let failed: Vec<_> = failed.into_iter().filter_map(Result::err).collect();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0.path(), Path::new("job.zip!/missing.log"));
+        assert_eq!(failed[0].1.kind(), std::io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn large_logs_searched_piece_by_piece_find_the_same_warnings() {
+        let settings_str = r#"
+        [rustc]
+        regex = "^warning: (?P<description>.+)\n\\s+--> (?P<file>[^:]+):(?P<line>\\d+):(?P<column>\\d+)$"
+        files = ["**/build.log"]
+        dedup = false
+        max_match_lines = 2
+        complete_marker = ["^\\s*Finished"]
+        "#;
+        let settings = toml::from_str::<Settings>(settings_str).unwrap();
+        let kind = Kind::new(settings.string_arena.get_id("rustc").unwrap());
+        let regexes = settings.kinds_and_regex();
+        let pattern = regexes.get(&kind).unwrap();
+        let log_file = Path::new("build.log");
+        let log = "   Compiling wcnt v0.4.0\n\
+                   warning: unused variable: `x`\n\
+                   \x20 --> src/main.rs:1:5\n\
+                   warning: unused variable: `y`\n\
+                   \x20 --> src/main.rs:2:5\n\
+                   warning: unused variable: `x`\n\
+                   \x20 --> src/main.rs:1:5\n\
+                   \x20   Finished dev [unoptimized]\n";
+
+        let limits = HashSet::new();
+        let whole = search_contents_with_regex(&limits, &kind, log_file, log, pattern);
+        // Split the log between the lines of every warning
+        let mut search = MultiKindSearch::new(&limits, log_file, &[(&kind, pattern)]);
+        let lines: Vec<&str> = log.split_inclusive('\n').collect();
+        for (i, line) in lines.iter().enumerate() {
+            search.search_piece(line.as_bytes(), i + 1 == lines.len()).unwrap();
+        }
+        let pieces = search.finish().pop().unwrap();
+
+        assert_eq!(pieces.incomplete, None);
+        let mut arena = SearchableArena::new();
+        let found_in_pieces = warnings_in(pieces, &mut arena);
+        assert_eq!(found_in_pieces.len(), 3);
+        assert_eq!(found_in_pieces, warnings_in(whole, &mut arena));
+    }
+
+    #[test]
+    fn anchors_match_in_later_pieces_as_in_the_whole_log() {
+        // Warnings end within their second line, with no `$`
+        let settings_str = r#"
+        [kind]
+        regex = "^(?P<file>[^:\\n]+):(?P<line>\\d+)\n(?P<description>\\w+)"
+        files = ["**/build.log"]
+        max_match_lines = 2
+        "#;
+        let settings = toml::from_str::<Settings>(settings_str).unwrap();
+        let kind = Kind::new(settings.string_arena.get_id("kind").unwrap());
+        let regexes = settings.kinds_and_regex();
+        let pattern = regexes.get(&kind).unwrap();
+        let log_file = Path::new("build.log");
+        // The line the warning ends in is searched again along with the next piece, after the end
+        // of the warning, where `^` must not match
+        let pieces = ["a.c:1\nunused b.c:2\nnoise\n", "noise\n"];
+        let log = pieces.concat();
+
+        let limits = HashSet::new();
+        let mut search = MultiKindSearch::new(&limits, log_file, &[(&kind, pattern)]);
+        for (i, piece) in pieces.iter().enumerate() {
+            search.search_piece(piece.as_bytes(), i + 1 == pieces.len()).unwrap();
+        }
+        let pieces = search.finish().pop().unwrap();
+        let whole = search_contents_with_regex(&limits, &kind, log_file, &log, pattern);
+
+        let mut arena = SearchableArena::new();
+        let found_in_pieces = warnings_in(pieces, &mut arena);
+        assert_eq!(found_in_pieces.len(), 1);
+        assert_eq!(found_in_pieces, warnings_in(whole, &mut arena));
+    }
+
+    #[test]
+    fn only_the_first_piece_of_a_large_log_may_have_a_byte_order_mark() {
+        let settings_str = r#"
+        [gcc]
+        regex = "^(?P<file>[^:\\n]+):(?P<line>\\d+): warning: (?P<description>.+)$"
+        files = ["**/build.log"]
+        encoding = "latin1"
+        "#;
+        let settings = toml::from_str::<Settings>(settings_str).unwrap();
+        let kind = Kind::new(settings.string_arena.get_id("gcc").unwrap());
+        let regexes = settings.kinds_and_regex();
+        let pattern = regexes.get(&kind).unwrap();
+        let log_file = Path::new("build.log");
+        // Lines starting with what would be byte order marks of UTF-16 and UTF-8 in the first piece
+        let pieces: [&[u8]; 3] = [
+            b"a.c:1: warning: x\n",
+            b"\xff\xfeb.c:2: warning: y\n",
+            b"\xef\xbb\xbfc.c:3: warning: \xe9\n",
+        ];
+
+        let limits = HashSet::new();
+        let mut search = MultiKindSearch::new(&limits, log_file, &[(&kind, pattern)]);
+        for (i, piece) in pieces.iter().enumerate() {
+            search.search_piece(piece, i + 1 == pieces.len()).unwrap();
+        }
+        let pieces = search.finish().pop().unwrap();
+        let log = concat!(
+            "a.c:1: warning: x\n",
+            "\u{ff}\u{fe}b.c:2: warning: y\n",
+            "\u{ef}\u{bb}\u{bf}c.c:3: warning: \u{e9}\n",
+        );
+        let whole = search_contents_with_regex(&limits, &kind, log_file, log, pattern);
+
+        let mut arena = SearchableArena::new();
+        let found_in_pieces = warnings_in(pieces, &mut arena);
+        assert_eq!(found_in_pieces.len(), 3);
+        assert_eq!(found_in_pieces, warnings_in(whole, &mut arena));
+    }
+
+    /// The warnings in the search `result`, remapped into the `arena`, to be compared with those of
+    /// other results.
+    fn warnings_in(
+        result: LogSearchResults,
+        arena: &mut SearchableArena,
+    ) -> HashSet<CountsTowardsLimit> {
+        arena.add_all(&result.string_arena);
+        let warnings = result.warnings.values().flatten();
+        warnings.map(|w| w.clone().remap(&result.string_arena, arena)).collect()
+    }
+
+    const MULTI_KIND_SETTINGS: &str = r#"
+        [gcc]
+        regex = "^(?P<file>[^:\\n]+):(?P<line>\\d+):(?P<column>\\d+): warning: (?P<description>.+) \\[(?P<category>.+)\\]$"
+        files = ["**/build.log"]
+        complete_marker = ["^Build succeeded"]
+
+        [rustc]
+        regex = "^warning: (?P<description>.+)\n\\s+--> (?P<file>[^:]+):(?P<line>\\d+):(?P<column>\\d+)$"
+        files = ["**/build.log"]
+        complete_marker = ["^\\s*Finished"]
+
+        [flake8]
+        regex = "^(?P<file>[^:\\n]+):(?P<line>\\d+):(?P<column>\\d+): (?P<category>[^\\s]+) (?P<description>.+)$"
+        files = ["**/build.log"]
+        encoding = "latin1"
+        "#;
+
+    /// A log of a build, compiling C and then Rust, with lots of noise in between.
+    fn multi_kind_log(noise_lines: usize) -> String {
+        let mut log = String::new();
+        for i in 1..=noise_lines {
+            log.push_str(&format!("gcc -c -O2 -Wall src/file{}.c -o obj/file{}.o\n", i, i));
+            if i % 1000 == 0 {
+                log.push_str(&format!("src/file{}.c:1:5: warning: unused `x` [-Wunused]\n", i));
+            }
+        }
+        for i in 1..=noise_lines {
+            log.push_str(&format!("   Compiling crate{} v0.1.0 (/src/crate{})\n", i, i));
+            if i % 1000 == 0 {
+                log.push_str(&format!("warning: unused `x`\n  --> src/crate{}.rs:1:5\n", i));
+            }
+        }
+        log.push_str("    Finished release [optimized]\n");
+        log
+    }
+
+    #[test]
+    fn compressed_logs_are_searched_piece_by_piece_whatever_their_size() {
+        use std::io::Write;
+
+        let settings = toml::from_str::<Settings>(MULTI_KIND_SETTINGS).unwrap();
+        let regexes = settings.kinds_and_regex();
+        let kinds = ["gcc", "rustc"]
+            .iter()
+            .map(|name| Kind::new(settings.string_arena.get_id(name).unwrap()))
+            .collect();
+        let lf = LogFile::new(PathBuf::from("build.log.gz"), kinds);
+        let groups = kinds_by_reading(&lf, &regexes);
+        let log = multi_kind_log(5000);
+        let limits = HashSet::new();
+
+        let dir = std::env::temp_dir().join(format!("wcnt-compressed-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (plain, gzip) = (dir.join("build.log"), dir.join("build.log.gz"));
+        std::fs::write(&plain, &log).unwrap();
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(log.as_bytes()).unwrap();
+        std::fs::write(&gzip, encoder.finish().unwrap()).unwrap();
+        let threshold = log.len() as u64;
+        let plain_file = FileSystemReader::open_large_file(&plain, threshold).unwrap();
+        let gzip_file = FileSystemReader::open_large_file(&gzip, threshold).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // Only the compressed log is streamed, even though it is below the threshold
+        assert!(plain_file.is_none());
+        let gzip_file = gzip_file.unwrap();
+        let streamed = search_large_file(&limits, lf.path(), gzip_file, &groups).unwrap().unwrap();
+        let whole = search_contents(&limits, lf.path(), &log, &groups[0]);
+        let mut arena = SearchableArena::new();
+        assert_eq!(streamed.len(), whole.len());
+        for (streamed, whole) in streamed.into_iter().zip(whole) {
+            let found = warnings_in(streamed, &mut arena);
+            assert_eq!(found.len(), 5);
+            assert_eq!(found, warnings_in(whole, &mut arena));
+        }
+    }
+
+    #[test]
+    fn kinds_reading_a_log_alike_are_searched_together() {
+        // Like in the README, the file of a gcc warning may match across lines, here across all the
+        // noise before the warning, which the whole log file is still searched for
+        let settings_str = MULTI_KIND_SETTINGS.replace(r"[^:\\n]+", "[^:]+");
+        let settings = toml::from_str::<Settings>(&settings_str).unwrap();
+        let kind = |name| Kind::new(settings.string_arena.get_id(name).unwrap());
+        let (gcc, rustc, flake8) = (kind("gcc"), kind("rustc"), kind("flake8"));
+        let regexes = settings.kinds_and_regex();
+        let all_kinds = vec![gcc.clone(), rustc.clone(), flake8.clone()];
+        let lf = LogFile::new(PathBuf::from("build.log"), all_kinds);
+        // Flake8 decodes the log differently
+        let groups = kinds_by_reading(&lf, &regexes);
+        let kinds_in = |group: &[(&Kind, &SearchPattern)]| -> Vec<Kind> {
+            group.iter().map(|(kind, _)| (*kind).clone()).collect()
+        };
+        assert_eq!(groups.len(), 2);
+        assert_eq!(kinds_in(&groups[0]), vec![gcc, rustc.clone()]);
+        assert_eq!(kinds_in(&groups[1]), vec![flake8]);
+
+        let log = multi_kind_log(5000);
+        let limits = HashSet::new();
+        let mut arena = SearchableArena::new();
+        let together = search_contents(&limits, lf.path(), &log, &groups[0]);
+        for (result, (kind, pattern)) in together.into_iter().zip(&groups[0]) {
+            // Without the prefilter, the regex of the kind searches the whole log
+            let mut search = ContentSearch::new(&limits, kind, lf.path(), pattern);
+            search.check_complete(&log);
+            search.search(&log, 0, 0, None);
+            let one_by_one = search.finish();
+            let is_rustc = **kind == rustc;
+            assert_eq!(result.incomplete.is_none(), is_rustc);
+            assert_eq!(one_by_one.incomplete, result.incomplete);
+            let found = warnings_in(result, &mut arena);
+            assert_eq!(found.len(), 5);
+            assert_eq!(found, warnings_in(one_by_one, &mut arena));
+        }
+    }
+
+    #[test]
+    #[ignore]
+    /// Compare searching a large log for several kinds at once, whole or piece by piece, with
+    /// searching the whole log once per kind. Run it with
+    /// `cargo test --release -- --ignored --nocapture bench`.
+    fn bench_kinds_searched_together_or_one_by_one() {
+        use std::time::Instant;
+
+        let settings = toml::from_str::<Settings>(MULTI_KIND_SETTINGS).unwrap();
+        let regexes = settings.kinds_and_regex();
+        let kinds = ["gcc", "rustc"]
+            .iter()
+            .map(|name| Kind::new(settings.string_arena.get_id(name).unwrap()))
+            .collect();
+        let lf = LogFile::new(PathBuf::from("build.log"), kinds);
+        let groups = kinds_by_reading(&lf, &regexes);
+        let limits = HashSet::new();
+        let count = |results: &[LogSearchResults]| -> usize {
+            results
+                .iter()
+                .map(|result| result.warnings.values().map(HashSet::len).sum::<usize>())
+                .sum()
+        };
+
+        let log = multi_kind_log(500_000);
+        // Only the Rust part of the log, without literals of gcc
+        let rust_log = &log[log.find("   Compiling").unwrap()..];
+        for (name, log) in [("C and Rust", log.as_str()), ("Rust", rust_log)] {
+            let start = Instant::now();
+            let one_by_one: Vec<_> = groups[0]
+                .iter()
+                .map(|(kind, pattern)| {
+                    let mut search = ContentSearch::new(&limits, kind, lf.path(), pattern);
+                    search.search(log, 0, 0, None);
+                    search.finish()
+                })
+                .collect();
+            let one_by_one_time = start.elapsed();
+
+            let start = Instant::now();
+            let together = search_contents(&limits, lf.path(), log, &groups[0]);
+            let together_time = start.elapsed();
+
+            let start = Instant::now();
+            let large_file: LargeFile = Box::new(std::io::Cursor::new(log.as_bytes().to_vec()));
+            let streamed = search_large_file(&limits, lf.path(), large_file, &groups);
+            let streamed = streamed.unwrap().unwrap();
+            let streamed_time = start.elapsed();
+
+            println!(
+                "{} MB log of {}, {} warnings: {:?} one kind at a time, {:?} all kinds at once, \
+                 {:?} all kinds piece by piece",
+                log.len() / (1024 * 1024),
+                name,
+                count(&together),
+                one_by_one_time,
+                together_time,
+                streamed_time
+            );
+            assert_eq!(count(&one_by_one), count(&together));
+            assert_eq!(count(&streamed), count(&together));
+        }
+    }
+
+    #[test]
+    fn logs_without_completion_markers_are_incomplete() {
+        let settings_str = r#"
+        [kind]
+        regex = "^(?P<file>[^:]+):(?P<line>\\d+): (?P<description>.+)$"
+        files = ["**/log.txt"]
+        complete_marker = ["^Build succeeded", "^Finished dev"]
+        "#;
+        let settings = toml::from_str::<Settings>(settings_str).unwrap();
+        let kind = Kind::new(settings.string_arena.get_id("kind").unwrap());
+        let regexes = settings.kinds_and_regex();
+        let pattern = regexes.get(&kind).unwrap();
+        let log_file = Path::new("log.txt");
+
+        let complete = "src/a.c:1: unused variable `x`\nFinished dev [unoptimized]\n";
+        let result =
+            search_contents_with_regex(&HashSet::new(), &kind, log_file, complete, pattern);
+        assert_eq!(result.incomplete, None);
+
+        let truncated = "src/a.c:1: unused variable `x`\n";
+        let result =
+            search_contents_with_regex(&HashSet::new(), &kind, log_file, truncated, pattern);
+        assert_eq!(result.incomplete, Some((kind, log_file.to_path_buf())));
+    }
+
+    #[test]
+    fn identity_decides_which_warnings_are_duplicates() {
+        let log = "src/a.c:1: unused variable `x`\n\
+                   src/a.c:1: unused variable `y`\n\
+                   src/a.c:1: unused variable `y`\n\
+                   src/a.c:2: unused variable `z`\n";
+
+        let by_default = r#"
+        [kind]
+        regex = "^(?P<file>[^:]+):(?P<line>\\d+): (?P<description>.+)$"
+        files = ["**/log.txt"]
+        "#;
+        assert_eq!(count_warnings(by_default, log), 3);
+
+        let without_description = r#"
+        [kind]
+        regex = "^(?P<file>[^:]+):(?P<line>\\d+): (?P<description>.+)$"
+        files = ["**/log.txt"]
+        identity = ["file", "line"]
+        "#;
+        assert_eq!(count_warnings(without_description, log), 2);
+
+        let every_occurrence = r#"
+        [kind]
+        regex = "^(?P<file>[^:]+):(?P<line>\\d+): (?P<description>.+)$"
+        files = ["**/log.txt"]
+        dedup = false
+        "#;
+        assert_eq!(count_warnings(every_occurrence, log), 4);
+    }
+
+    #[test]
+    fn category_template_is_built_from_captures() {
+        let settings_str = r#"
+        [tidy]
+        regex = "^(?P<file>[^:]+): (?P<severity>\\w+): .+ \\[(?P<tool>\\w+): (?P<code>\\w+)\\]$"
+        files = ["**/tidy.txt"]
+        category = "{severity}/{tool}:{code}"
+        "#;
+        let settings = toml::from_str::<Settings>(settings_str).unwrap();
+        let kind = Kind::new(settings.string_arena.get_id("tidy").unwrap());
+        let regexes = settings.kinds_and_regex();
+        let pattern = regexes.get(&kind).unwrap();
+
+        let limits: HashSet<PathBuf> = vec![PathBuf::from("src/Limits.toml")].into_iter().collect();
+        let log = "src/a.c: warning: bad cast [cert: EXP36]\n\
+                   src/b.c: error: bad move [bugprone: M01]\n";
+        let result =
+            search_contents_with_regex(&limits, &kind, Path::new("tidy.txt"), log, pattern);
+        let arena = &result.string_arena;
+        let categories: HashSet<&str> = result
+            .warnings
+            .keys()
+            .map(|entry| entry.category.to_str(arena).unwrap())
+            .collect();
+        let expected: HashSet<&str> =
+            vec!["warning/cert:EXP36", "error/bugprone:M01"].into_iter().collect();
+        assert_eq!(categories, expected);
+    }
+
+    #[test]
+    fn grouped_kinds_are_deduplicated_across_kinds() {
+        let settings_str = r#"
+        [gcc]
+        regex = "^(?P<file>[^:]+):(?P<line>\\d+):(?P<column>\\d+): warning: (?P<description>.+) \\[(?P<category>.+)\\]$"
+        files = ["**/gcc.txt"]
+        group = "c-compilers"
+
+        [clang]
+        regex = "^(?P<file>[^:]+):(?P<line>\\d+):(?P<column>\\d+): warning: (?P<description>.+) \\[(?P<category>.+)\\]$"
+        files = ["**/clang.txt"]
+        group = "c-compilers"
+        "#;
+        let mut settings = toml::from_str::<Settings>(settings_str).unwrap();
+        let gcc = Kind::new(settings.string_arena.get_id("gcc").unwrap());
+        let clang = Kind::new(settings.string_arena.get_id("clang").unwrap());
+        let group = Kind::new(settings.string_arena.get_id("c-compilers").unwrap());
+        let regexes = settings.kinds_and_regex();
+
+        let gcc_log = "src/a.c:1:5: warning: unused variable 'x' [-Wunused-variable]\n";
+        let clang_log = "src/a.c:1:9: warning: unused variable `x` [-Wunused-variable]\n\
+                         src/a.c:2:1: warning: unused variable `y` [-Wunused-variable]\n";
+        let results = vec![
+            (gcc.clone(), gcc_log, Path::new("gcc.txt")),
+            (clang.clone(), clang_log, Path::new("clang.txt")),
+        ];
+
+        let mut merged = HashMap::new();
+        for (kind, log, log_file) in results {
+            let pattern = regexes.get(&kind).unwrap();
+            let LogSearchResults {
+                string_arena: incoming,
+                warnings: found,
+                ..
+            } = search_contents_with_regex(&HashSet::new(), &kind, log_file, log, pattern);
+            settings.string_arena.add_all(&incoming);
+            for (entry, warnings) in found {
+                assert_eq!(entry.kind, group);
+                merged
+                    .entry(entry.kind)
+                    .or_insert_with(HashSet::new)
+                    .extend(
+                        warnings
+                            .into_iter()
+                            .map(|w| w.remap(&incoming, &settings.string_arena)),
+                    );
+            }
+        }
+        assert_eq!(merged.get(&group).map(HashSet::len), Some(2));
+    }
 }
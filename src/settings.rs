@@ -5,14 +5,17 @@
 use std::borrow::Cow;
 use std::collections::{HashSet, HashMap};
 use std::fmt::Display;
+use std::path::PathBuf;
 
 use id_arena::Id;
 use linked_hash_map::LinkedHashMap;
 use regex::{Regex, RegexBuilder};
 use serde::{Deserialize, Deserializer};
 
+use crate::limits::{Category, Limit, Severity, Threshold};
 use crate::utils;
 use crate::utils::SearchableArena;
+use crate::warnings::{CountsTowardsLimit, Identity};
 
 #[derive(Debug, PartialEq, Eq, Ord, PartialOrd, Hash, Clone)]
 /// A Kind is a kind of warnings, all matchable with the same regular expression.
@@ -35,49 +38,267 @@ impl Kind {
 pub(crate) struct Settings {
     pub(crate) string_arena: SearchableArena,
     inner: LinkedHashMap<Kind, SettingsField>,
+    max_totals: LinkedHashMap<Kind, Limit>,
+    weights: Weights,
+    groups: LinkedHashMap<Kind, Vec<Kind>>,
+    limits_file_name: String,
+    central_limits: Option<CentralLimitsSource>,
+    strict: bool,
+    stale_logs: StaleCheck,
+    missing_culprits: StaleCheck,
+    log_roots: Vec<PathBuf>,
+    streaming_threshold: u64,
     kinds_to_ignore: HashSet<Kind>,
 }
 
+/// The key of the table holding [centralized limits](enum.CentralLimitsSource.html).
+pub(crate) const CENTRAL_LIMITS_KEY: &str = "limits";
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+/// Where the limits are declared when they are centralized, rather than spread out in limits files
+/// throughout the tree. Either in a `limits` table in Wcnt.toml itself, or in the file named by
+/// `central_limits`, relative to Wcnt.toml.
+pub(crate) enum CentralLimitsSource {
+    Settings,
+    File(PathBuf),
+}
+
+/// Log files larger than this many megabytes are searched piece by piece, unless
+/// `streaming_threshold_mb` says otherwise.
+pub(crate) const DEFAULT_STREAMING_THRESHOLD_MB: u64 = 256;
+
+/// The most lines a warning of a [Kind](struct.Kind.html) spans, unless `max_match_lines` says
+/// otherwise.
+pub(crate) const DEFAULT_MAX_MATCH_LINES: usize = 100;
+
+/// The name of the files declaring limits, unless `limits_file` says otherwise.
+pub(crate) const DEFAULT_LIMITS_FILE_NAME: &str = "Limits.toml";
+
+#[derive(Debug, Default)]
+/// The weights warnings count with, when a limit is expressed as a `score`. A warning weighs the
+/// product of the weights of its [Kind](struct.Kind.html), category and severity, where anything
+/// not given a weight in Wcnt.toml weighs 1.
+pub(crate) struct Weights {
+    inner: HashMap<Kind, KindWeights>,
+}
+
+#[derive(Debug, PartialEq)]
+struct KindWeights {
+    weight: u64,
+    categories: HashMap<Category, u64>,
+    severities: HashMap<Severity, u64>,
+}
+
+impl Weights {
+    pub(crate) fn weight_of(&self, warning: &CountsTowardsLimit) -> u64 {
+        match self.inner.get(warning.kind()) {
+            Some(kind_weights) => {
+                let category = kind_weights.categories.get(warning.category()).unwrap_or(&1);
+                let severity = kind_weights.severities.get(warning.severity()).unwrap_or(&1);
+                kind_weights.weight * category * severity
+            }
+            None => 1,
+        }
+    }
+}
+
 pub(crate) struct RelevantRegexes {
-    inner: HashMap<Kind, Regex>,
+    inner: HashMap<Kind, SearchPattern>,
 }
 
 impl RelevantRegexes {
-    pub(crate) fn get(&self, kind: &Kind) -> Option<&Regex> {
+    pub(crate) fn get(&self, kind: &Kind) -> Option<&SearchPattern> {
         self.inner.get(kind)
     }
 }
 
+#[derive(Debug, Clone)]
+/// Everything needed to search for, and interpret, warnings of a [Kind](struct.Kind.html) inside a
+/// log file.
+pub(crate) struct SearchPattern {
+    pub(crate) regex: Regex,
+    pub(crate) severity: SeveritySettings,
+    pub(crate) category: CategorySettings,
+    pub(crate) identity: Identity,
+    pub(crate) dedup: bool,
+    pub(crate) group: Option<Kind>,
+    pub(crate) complete_markers: Vec<Regex>,
+    pub(crate) preprocessing: Preprocessing,
+    pub(crate) encoding: Encoding,
+    pub(crate) lossy_decoding: bool,
+    pub(crate) max_match_lines: usize,
+}
+
+impl SearchPattern {
+    /// Does this pattern read log files the same way as the `other` one, i.e. decode and preprocess
+    /// them alike?
+    pub(crate) fn reads_logs_like(&self, other: &SearchPattern) -> bool {
+        self.encoding == other.encoding
+            && self.lossy_decoding == other.lossy_decoding
+            && self.preprocessing == other.preprocessing
+    }
+}
+
 impl Settings {
     pub fn iter(&self) -> impl Iterator<Item = (&Kind, &SettingsField)> {
         self.inner.iter()
     }
 
+    pub(crate) fn get(&self, kind: &Kind) -> Option<&SettingsField> {
+        self.inner.get(kind)
+    }
+
     pub(crate) fn kinds_and_regex(&self) -> RelevantRegexes {
         RelevantRegexes {
-            inner: self.kinds().map(|k|
-                (k.clone(), self.inner.get(k).unwrap().regex.clone())
-            ).collect(),
+            inner: self.kinds().map(|k| {
+                let field = self.inner.get(k).unwrap();
+                let pattern = SearchPattern {
+                    regex: field.regex.clone(),
+                    severity: field.severity.clone(),
+                    category: field.category.clone(),
+                    identity: field.identity,
+                    dedup: field.dedup,
+                    group: self.group_of(k).cloned(),
+                    complete_markers: field.complete_markers.clone(),
+                    preprocessing: field.preprocessing.clone(),
+                    encoding: field.encoding,
+                    lossy_decoding: field.lossy_decoding,
+                    max_match_lines: field.max_match_lines,
+                };
+                (k.clone(), pattern)
+            }).collect(),
         }
     }
 
+    /// The kinds, and groups of kinds, which may have limits per category. A group may only have
+    /// that if all of its members capture categories.
     pub(crate) fn categorizables(&self) -> HashSet<Kind> {
+        self.limitable_with(|sf| sf.categorizable)
+    }
+
+    /// The kinds, and groups of kinds, which may have limits per severity. A group may only have
+    /// that if all of its members capture severities.
+    pub(crate) fn severitizables(&self) -> HashSet<Kind> {
+        self.limitable_with(|sf| sf.severitizable)
+    }
+
+    fn limitable_with(&self, pred: impl Fn(&SettingsField) -> bool) -> HashSet<Kind> {
         let mut result = HashSet::new();
         for (kind, sf) in self.iter() {
-            if sf.categorizable {
+            if pred(sf) {
                 result.insert(kind.clone());
             }
         }
+        for (group, members) in &self.groups {
+            if members.iter().all(|m| result.contains(m)) {
+                result.insert(group.clone());
+            }
+        }
+        result
+    }
+
+    /// The group the `kind` counts towards, if it is a member of one.
+    pub(crate) fn group_of(&self, kind: &Kind) -> Option<&Kind> {
+        self.groups
+            .iter()
+            .find(|(_group, members)| members.contains(kind))
+            .map(|(group, _members)| group)
+    }
+
+    /// The kinds and groups which are addressed in the Limits.toml files, and will be counted in
+    /// full during this run. A group is only counted in full if all of its members run.
+    pub(crate) fn limit_kinds(&self) -> HashSet<&Kind> {
+        let mut result: HashSet<&Kind> =
+            self.kinds().filter(|k| self.group_of(k).is_none()).collect();
+        for (group, members) in &self.groups {
+            if members.iter().all(|m| !self.should_skip_kind(m)) {
+                result.insert(group);
+            }
+        }
         result
     }
 
+    /// The project wide [Limit](../limits/enum.Limit.html)s declared using `max_total`, for the
+    /// kinds we should run. The total of a kind in a group is the total of the group.
+    pub(crate) fn max_totals(&self) -> impl Iterator<Item = (&Kind, &Limit)> {
+        self.max_totals
+            .iter()
+            .filter(move |(k, _limit)| match self.groups.get(k) {
+                Some(members) => members.iter().any(|m| !self.should_skip_kind(m)),
+                None => !self.should_skip_kind(k),
+            })
+    }
+
+    /// The name of the files declaring limits. The format of the files is decided by the extension.
+    pub(crate) fn limits_file_name(&self) -> &str {
+        &self.limits_file_name
+    }
+
+    /// In strict mode, every culprit must be covered by a limits file.
+    pub(crate) fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// What to do about log files older than the newest limits file or culprit they report on.
+    pub(crate) fn stale_logs(&self) -> StaleCheck {
+        self.stale_logs
+    }
+
+    /// What to do about warnings whose culprit does not exist.
+    pub(crate) fn missing_culprits(&self) -> StaleCheck {
+        self.missing_culprits
+    }
+
+    /// The directories to search for log files in, instead of the start directory. Relative to
+    /// Wcnt.toml.
+    pub(crate) fn log_roots(&self) -> &[PathBuf] {
+        &self.log_roots
+    }
+
+    /// The size in bytes above which log files are searched piece by piece, rather than read whole.
+    /// Log files inside archives are always read whole.
+    pub(crate) fn streaming_threshold(&self) -> u64 {
+        self.streaming_threshold
+    }
+
+    /// The limit for warnings of the `kind` (or group) without any other limit declared. For a
+    /// group, the strictest default limit of its kinds is used. None means infinity.
+    pub(crate) fn default_limit(&self, kind: &Kind) -> Option<u64> {
+        match self.inner.get(kind) {
+            Some(field) => field.default_limit,
+            None => self
+                .groups
+                .get(kind)
+                .into_iter()
+                .flatten()
+                .filter_map(|member| self.inner.get(member))
+                .map(|field| field.default_limit)
+                .min_by_key(|limit| limit.unwrap_or(u64::MAX))
+                .unwrap_or(Some(0)),
+        }
+    }
+
+    pub(crate) fn central_limits(&self) -> Option<&CentralLimitsSource> {
+        self.central_limits.as_ref()
+    }
+
+    pub(crate) fn weights(&self) -> &Weights {
+        &self.weights
+    }
+
     pub fn kinds<'me>(&'me self) -> impl Iterator<Item=&'me Kind> + 'me {
         self.inner.keys().filter(move |k| !self.should_skip_kind(k))
     }
 
     pub fn configure_kinds_to_run(&mut self, kinds_to_ignore: &Option<Vec<String>>) {
         if let Some(only_these) = kinds_to_ignore {
-            let as_kinds: HashSet<Kind> = only_these.iter().map(|k| self.string_arena.get_id(k)).flatten().map(Kind::new).collect();
+            let mut as_kinds: HashSet<Kind> = only_these.iter().filter_map(|k| self.string_arena.get_id(k)).map(Kind::new).collect();
+            // Running a group means running all of its members
+            for (group, members) in &self.groups {
+                if as_kinds.contains(group) {
+                    as_kinds.extend(members.iter().cloned());
+                }
+            }
             let tmp = self.kinds().filter(|k| !as_kinds.contains(k)).cloned();
             self.kinds_to_ignore = tmp.collect();
         }
@@ -90,10 +311,59 @@ impl Settings {
     pub fn display<'me>(&'me self) -> impl Display + 'me {
         utils::fmt_helper(move |f| {
             writeln!(f, "Settings {{")?;
+            writeln!(f, "limits_file = {:?}", self.limits_file_name)?;
+            if let Some(central_limits) = &self.central_limits {
+                writeln!(f, "central_limits = {:?}", central_limits)?;
+            }
+            writeln!(f, "strict = {}", self.strict)?;
+            writeln!(f, "stale_logs = {:?}", self.stale_logs)?;
+            writeln!(f, "missing_culprits = {:?}", self.missing_culprits)?;
+            if !self.log_roots.is_empty() {
+                writeln!(f, "log_roots = {:?}", self.log_roots)?;
+            }
+            writeln!(f, "streaming_threshold = {}", self.streaming_threshold)?;
             for (kind, field) in &self.inner {
                 writeln!(f, "[{}]", kind.to_str(&self.string_arena))?;
                 writeln!(f, "regex = {:?}", field.regex)?;
                 writeln!(f, "files = [{}]", field.files.join(", "))?;
+                if field.severitizable {
+                    writeln!(f, "severity = {:?}", field.severity)?;
+                }
+                if field.categorizable {
+                    writeln!(f, "category = {:?}", field.category)?;
+                }
+                if field.identity != Identity::default() {
+                    writeln!(f, "identity = {:?}", field.identity)?;
+                }
+                if !field.dedup {
+                    writeln!(f, "dedup = false")?;
+                }
+                if let Some(group) = self.group_of(kind) {
+                    writeln!(f, "group = {}", group.to_str(&self.string_arena))?;
+                }
+                writeln!(f, "default_limit = {:?}", field.default_limit)?;
+                if field.min_files > 0 {
+                    writeln!(f, "min_files = {}", field.min_files)?;
+                }
+                if !field.complete_markers.is_empty() {
+                    writeln!(f, "complete_marker = {:?}", field.complete_markers)?;
+                }
+                writeln!(f, "preprocess = {:?}", field.preprocessing)?;
+                writeln!(f, "encoding = {:?}", field.encoding)?;
+                if field.lossy_decoding {
+                    writeln!(f, "lossy_decoding = true")?;
+                }
+                writeln!(f, "max_match_lines = {}", field.max_match_lines)?;
+                writeln!(f, "outside_tree = {:?}", field.outside_tree)?;
+                if let Some(limit) = field.outside_tree_limit {
+                    writeln!(f, "outside_tree_limit = {}", limit)?;
+                }
+                if let Some(max_total) = &field.max_total {
+                    writeln!(f, "max_total = {:?}", max_total)?;
+                }
+                if let Some(weights) = self.weights.inner.get(kind) {
+                    writeln!(f, "weights = {:?}", weights)?;
+                }
             }
             write!(f, "}}")
         })
@@ -106,7 +376,369 @@ impl Settings {
 pub(crate) struct SettingsField {
     pub(crate) regex: Regex,
     pub(crate) files: Vec<String>,
+    pub(crate) max_total: Option<MaxTotal>,
+    pub(crate) severity: SeveritySettings,
+    pub(crate) category: CategorySettings,
+    weight: Option<u64>,
+    category_weights: HashMap<String, u64>,
+    severity_weights: HashMap<String, u64>,
+    identity: Identity,
+    dedup: bool,
+    group: Option<String>,
+    pub(crate) outside_tree: OutsideTree,
+    pub(crate) outside_tree_limit: Option<u64>,
+    default_limit: Option<u64>,
+    pub(crate) min_files: usize,
+    complete_markers: Vec<Regex>,
+    preprocessing: Preprocessing,
+    encoding: Encoding,
+    lossy_decoding: bool,
+    max_match_lines: usize,
     categorizable: bool,
+    severitizable: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+/// What to do about stale findings, i.e. log files older than the files they report on, or
+/// culprits which no longer exist. Either `ignore` them without checking, `warn` about them,
+/// `fail` because of them, or `drop` the warnings they concern.
+pub(crate) enum StaleCheck {
+    #[default]
+    Ignore,
+    Warn,
+    Fail,
+    Drop,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+/// What to do with warnings of a [Kind](struct.Kind.html) whose culprit is outside the source
+/// tree, so there is no limits file for it. Either `ignore` them, `fail` because of them, or
+/// `count` them towards the `outside_tree_limit` of the kind, which is 0 unless given.
+pub(crate) enum OutsideTree {
+    Ignore,
+    Fail,
+    #[default]
+    Count,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// How the captured `severity` of a [Kind](struct.Kind.html) should be interpreted. Tools spell
+/// their severities differently, so `map` translates them into the names used in the Limits.toml
+/// files. Warnings with a severity in `ignore` are not counted at all.
+pub(crate) struct SeveritySettings {
+    #[serde(default)]
+    map: HashMap<String, String>,
+    #[serde(default)]
+    ignore: HashSet<String>,
+}
+
+impl SeveritySettings {
+    /// Translate the severity as written by the tool, into the name used in the Limits.toml files.
+    pub(crate) fn normalize<'a>(&'a self, raw: &'a str) -> &'a str {
+        self.map.get(raw).map(String::as_str).unwrap_or(raw)
+    }
+
+    pub(crate) fn should_ignore(&self, severity: &str) -> bool {
+        self.ignore.contains(severity)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Deserialize)]
+/// The encoding of the log files of a [Kind](struct.Kind.html), unless they start with a byte
+/// order mark saying otherwise.
+pub(crate) enum Encoding {
+    #[default]
+    #[serde(rename = "utf-8")]
+    Utf8,
+    #[serde(rename = "utf-16le")]
+    Utf16Le,
+    #[serde(rename = "utf-16be")]
+    Utf16Be,
+    #[serde(rename = "latin1")]
+    Latin1,
+}
+
+impl Encoding {
+    /// Can a log file in this encoding, starting with the bytes `start`, be decoded piece by piece
+    /// when split after any `\n` byte? Not if it is in UTF-16, where that byte may be half a
+    /// character.
+    pub(crate) fn is_splittable_at_newlines(self, start: &[u8]) -> bool {
+        match start {
+            [0xEF, 0xBB, 0xBF, ..] => true,
+            [0xFF, 0xFE, ..] | [0xFE, 0xFF, ..] => false,
+            _ => self == Encoding::Utf8 || self == Encoding::Latin1,
+        }
+    }
+
+    /// Decode the `bytes` of a log file, in this encoding unless there is a byte order mark.
+    /// Invalid data is an error, unless the decoding is `lossy`, which replaces it with U+FFFD.
+    pub(crate) fn decode(self, bytes: &[u8], lossy: bool) -> Result<Cow<'_, str>, String> {
+        let (encoding, bytes) = self.after_byte_order_mark(bytes);
+        encoding.decode_exactly(bytes, lossy)
+    }
+
+    /// The encoding of a log file starting with the `bytes`, which is this one unless there is a
+    /// byte order mark, along with the bytes after that mark.
+    pub(crate) fn after_byte_order_mark(self, bytes: &[u8]) -> (Encoding, &[u8]) {
+        match bytes {
+            [0xEF, 0xBB, 0xBF, rest @ ..] => (Encoding::Utf8, rest),
+            [0xFF, 0xFE, rest @ ..] => (Encoding::Utf16Le, rest),
+            [0xFE, 0xFF, rest @ ..] => (Encoding::Utf16Be, rest),
+            _ => (self, bytes),
+        }
+    }
+
+    /// Decode the `bytes` in this encoding, without looking for a byte order mark, as for a piece
+    /// of a log file after the first one.
+    pub(crate) fn decode_exactly(self, bytes: &[u8], lossy: bool) -> Result<Cow<'_, str>, String> {
+        match self {
+            Encoding::Utf8 if lossy => Ok(String::from_utf8_lossy(bytes)),
+            Encoding::Utf8 => std::str::from_utf8(bytes)
+                .map(Cow::Borrowed)
+                .map_err(|e| e.to_string()),
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let chunks = bytes.chunks_exact(2);
+                let has_odd_byte = !chunks.remainder().is_empty();
+                let units = chunks.map(|pair| match self {
+                    Encoding::Utf16Le => u16::from_le_bytes([pair[0], pair[1]]),
+                    _ => u16::from_be_bytes([pair[0], pair[1]]),
+                });
+                if lossy {
+                    let mut result: String = char::decode_utf16(units)
+                        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                        .collect();
+                    if has_odd_byte {
+                        result.push(char::REPLACEMENT_CHARACTER);
+                    }
+                    Ok(Cow::Owned(result))
+                } else if has_odd_byte {
+                    Err("UTF-16 data with an odd number of bytes".to_owned())
+                } else {
+                    char::decode_utf16(units)
+                        .collect::<Result<String, _>>()
+                        .map(Cow::Owned)
+                        .map_err(|e| e.to_string())
+                }
+            }
+            // Every byte is a code point of its own
+            Encoding::Latin1 => Ok(Cow::Owned(bytes.iter().map(|&b| char::from(b)).collect())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// How the log files of a [Kind](struct.Kind.html) are cleaned up, before they are searched for
+/// warnings. Line endings are normalized first, then ANSI escape codes are stripped, and last the
+/// prefix matching `strip_prefix` is stripped from every line.
+pub(crate) struct Preprocessing {
+    normalize_line_endings: bool,
+    ansi_escapes: Option<Regex>,
+    strip_prefix: Option<Regex>,
+}
+
+/// Matches the ANSI escape codes used for colored output, and the like. That is control sequences,
+/// operating system commands, and any other escape.
+const ANSI_ESCAPES: &str = concat!(
+    r"\x1b\[[0-9;?]*[ -/]*[@-~]",
+    r"|\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)",
+    r"|\x1b[@-_]",
+);
+
+impl Preprocessing {
+    /// Clean up the `contents` of a log file, without copying it unless anything changes.
+    pub(crate) fn apply<'a>(&self, contents: &'a str) -> Cow<'a, str> {
+        let mut result = Cow::Borrowed(contents);
+        if self.normalize_line_endings && result.contains('\r') {
+            result = Cow::Owned(result.replace("\r\n", "\n"));
+        }
+        for regex in self.ansi_escapes.iter().chain(self.strip_prefix.iter()) {
+            result = strip_matches(regex, result);
+        }
+        result
+    }
+}
+
+impl PartialEq for Preprocessing {
+    fn eq(&self, other: &Self) -> bool {
+        // Regexes are told apart by the pattern they were built from
+        self.normalize_line_endings == other.normalize_line_endings
+            && self.ansi_escapes.as_ref().map(Regex::as_str)
+                == other.ansi_escapes.as_ref().map(Regex::as_str)
+            && self.strip_prefix.as_ref().map(Regex::as_str)
+                == other.strip_prefix.as_ref().map(Regex::as_str)
+    }
+}
+
+fn strip_matches<'a>(regex: &Regex, text: Cow<'a, str>) -> Cow<'a, str> {
+    let stripped = regex.replace_all(&text, "");
+    if let Cow::Owned(stripped) = stripped {
+        return Cow::Owned(stripped);
+    }
+    text
+}
+
+impl<'de> Deserialize<'de> for Preprocessing {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct RawPreprocessing {
+            #[serde(default)]
+            normalize_line_endings: bool,
+            #[serde(default)]
+            strip_ansi: bool,
+            strip_prefix: Option<String>,
+        }
+
+        let raw = RawPreprocessing::deserialize(deserializer)?;
+        let ansi_escapes = if raw.strip_ansi {
+            Some(Regex::new(ANSI_ESCAPES).expect("Regex for ANSI escapes is valid"))
+        } else {
+            None
+        };
+        // The prefix is only ever stripped from the start of a line
+        let strip_prefix = match raw.strip_prefix {
+            Some(prefix) => Some(
+                RegexBuilder::new(&format!("^(?:{})", prefix))
+                    .multi_line(true)
+                    .build()
+                    .map_err(serde::de::Error::custom)?,
+            ),
+            None => None,
+        };
+        Ok(Preprocessing {
+            normalize_line_endings: raw.normalize_line_endings,
+            ansi_escapes: ansi_escapes,
+            strip_prefix: strip_prefix,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// How the captured `category` of a [Kind](struct.Kind.html) is normalized, before it is matched
+/// against the categories in the Limits.toml files. Prefixes and suffixes are stripped first, then
+/// the case is folded, and last the `category_aliases` translate the result into its canonical
+/// name.
+pub(crate) struct CategorySettings {
+    #[serde(default)]
+    fold_case: bool,
+    #[serde(default)]
+    strip_prefixes: Vec<String>,
+    #[serde(default)]
+    strip_suffixes: Vec<String>,
+    #[serde(skip)]
+    aliases: HashMap<String, String>,
+    #[serde(skip)]
+    template: Option<CategoryTemplate>,
+}
+
+impl CategorySettings {
+    /// The category of a warning, before it is normalized. Either built from the `category`
+    /// template, or taken from the `category` capture group.
+    /// The text captured by each named group of the match is looked up with `group`.
+    pub(crate) fn capture<'t, F>(&self, group: F) -> Option<Cow<'t, str>>
+    where
+        F: Fn(&str) -> Option<&'t str>,
+    {
+        match &self.template {
+            Some(template) => Some(Cow::Owned(template.render(group))),
+            None => group("category").map(Cow::Borrowed),
+        }
+    }
+
+    /// Translate the category as written by the tool, into the name used in the Limits.toml files.
+    pub(crate) fn normalize<'a>(&'a self, raw: &'a str) -> Cow<'a, str> {
+        let mut result = raw;
+        let prefix = self.strip_prefixes.iter().find_map(|p| result.strip_prefix(p.as_str()));
+        if let Some(stripped) = prefix {
+            result = stripped;
+        }
+        let suffix = self.strip_suffixes.iter().find_map(|s| result.strip_suffix(s.as_str()));
+        if let Some(stripped) = suffix {
+            result = stripped;
+        }
+        let folded = if self.fold_case {
+            Cow::Owned(result.to_lowercase())
+        } else {
+            Cow::Borrowed(result)
+        };
+        match self.aliases.get(folded.as_ref()) {
+            Some(alias) => Cow::Borrowed(alias.as_str()),
+            None => folded,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A template such as `"{tool}:{code}"`, deriving the category of a warning from any of the named
+/// capture groups of the regex.
+struct CategoryTemplate {
+    parts: Vec<TemplatePart>,
+}
+
+#[derive(Debug, Clone)]
+enum TemplatePart {
+    Literal(String),
+    Capture(String),
+}
+
+impl CategoryTemplate {
+    fn parse(template: &str, captures: &HashSet<&str>) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..].find('}').map(|end| start + end).ok_or_else(|| {
+                format!("Category template `{}` has an unclosed `{{`.", template)
+            })?;
+            if start > 0 {
+                parts.push(TemplatePart::Literal(rest[..start].to_owned()));
+            }
+            let name = &rest[start + 1..end];
+            if !captures.contains(name) {
+                return Err(format!(
+                    "Category template `{}` uses `{}`, which is not captured by the regex.",
+                    template, name
+                ));
+            }
+            parts.push(TemplatePart::Capture(name.to_owned()));
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(TemplatePart::Literal(rest.to_owned()));
+        }
+        Ok(CategoryTemplate { parts: parts })
+    }
+
+    /// Fill in the template. Capture groups that did not participate in the match are left empty.
+    fn render<'t, F: Fn(&str) -> Option<&'t str>>(&self, group: F) -> String {
+        let mut result = String::new();
+        for part in &self.parts {
+            match part {
+                TemplatePart::Literal(text) => result.push_str(text),
+                TemplatePart::Capture(name) => {
+                    if let Some(captured) = group(name) {
+                        result.push_str(captured);
+                    }
+                }
+            }
+        }
+        result
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+/// A project wide cap on the number of warnings of a [Kind](struct.Kind.html), summed over all
+/// Limits.toml files. Either for all warnings, or per category.
+pub(crate) enum MaxTotal {
+    Number(u64),
+    PerCategory(LinkedHashMap<String, u64>),
 }
 
 impl<'de> Deserialize<'de> for Settings {
@@ -114,8 +746,58 @@ impl<'de> Deserialize<'de> for Settings {
     where
         D: Deserializer<'de>,
     {
-        let raw = <LinkedHashMap<String, SettingsField>>::deserialize(deserializer)?;
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct RawGlobals {
+            limits_file: Option<String>,
+            central_limits: Option<String>,
+            #[serde(default)]
+            strict: bool,
+            #[serde(default)]
+            stale_logs: StaleCheck,
+            #[serde(default)]
+            missing_culprits: StaleCheck,
+            #[serde(default)]
+            log_roots: Vec<PathBuf>,
+            streaming_threshold_mb: Option<u64>,
+        }
+
+        // Tables declare kinds, any other top level values are options for the whole project
+        let raw_values = <LinkedHashMap<String, toml::Value>>::deserialize(deserializer)?;
+        let mut raw = LinkedHashMap::new();
+        let mut raw_globals = toml::value::Table::new();
+        let mut has_inline_limits = false;
+        for (key, val) in raw_values {
+            if key == CENTRAL_LIMITS_KEY {
+                has_inline_limits = true;
+            } else if val.is_table() {
+                let field: SettingsField = val.try_into().map_err(|e| {
+                    serde::de::Error::custom(format!("{} for key `{}`", e, key))
+                })?;
+                raw.insert(key, field);
+            } else {
+                raw_globals.insert(key, val);
+            }
+        }
+        let globals: RawGlobals = toml::Value::Table(raw_globals)
+            .try_into()
+            .map_err(serde::de::Error::custom)?;
+
+        let central_limits = match (has_inline_limits, globals.central_limits) {
+            (true, Some(_)) => {
+                return Err(serde::de::Error::custom(
+                    "Limits are given in both the `limits` table and in `central_limits`.",
+                ))
+            }
+            (true, None) => Some(CentralLimitsSource::Settings),
+            (false, Some(file)) => Some(CentralLimitsSource::File(PathBuf::from(file))),
+            (false, None) => None,
+        };
+
         let mut result = LinkedHashMap::new();
+        let mut max_totals = LinkedHashMap::new();
+        let mut weights = Weights::default();
+        let mut groups: LinkedHashMap<Kind, Vec<Kind>> = LinkedHashMap::new();
         let mut string_arena = SearchableArena::new();
         for (key, val) in raw.into_iter() {
             let captures: HashSet<&str> = val.regex.capture_names().flatten().collect();
@@ -127,12 +809,116 @@ impl<'de> Deserialize<'de> for Settings {
                 return Err(serde::de::Error::custom(msg));
             }
 
-            let kind_id = string_arena.insert(key);
-            result.insert(Kind(kind_id), val);
+            let max_total = match &val.max_total {
+                None => None,
+                Some(MaxTotal::Number(x)) => Some(Limit::Number(Threshold::warnings(Some(*x)))),
+                Some(MaxTotal::PerCategory(dict)) => {
+                    if !val.categorizable {
+                        let msg = format!(
+                            "Kind '{}' has a per category `max_total` but does not capture `category`.",
+                            key
+                        );
+                        return Err(serde::de::Error::custom(msg));
+                    }
+                    let per_category = dict
+                        .iter()
+                        .map(|(cat_str, x)| {
+                            let category = Category::from_str(cat_str, &mut string_arena);
+                            (category, Threshold::warnings(Some(*x)))
+                        })
+                        .collect();
+                    Some(Limit::PerCategory(per_category))
+                }
+            };
+
+            let kind = Kind(string_arena.get_or_insert(&key));
+            if let Some(limit) = max_total {
+                max_totals.insert(kind.clone(), limit);
+            }
+            if val.weight.is_some()
+                || !val.category_weights.is_empty()
+                || !val.severity_weights.is_empty()
+            {
+                let kind_weights = KindWeights {
+                    weight: val.weight.unwrap_or(1),
+                    categories: val
+                        .category_weights
+                        .iter()
+                        .map(|(cat_str, x)| (Category::from_str(cat_str, &mut string_arena), *x))
+                        .collect(),
+                    severities: val
+                        .severity_weights
+                        .iter()
+                        .map(|(sev_str, x)| {
+                            (Severity::new(string_arena.get_or_insert(sev_str)), *x)
+                        })
+                        .collect(),
+                };
+                weights.inner.insert(kind.clone(), kind_weights);
+            }
+            if let Some(group) = &val.group {
+                let group = Kind(string_arena.get_or_insert(group));
+                groups.entry(group).or_default().push(kind.clone());
+            }
+            result.insert(kind, val);
+        }
+        if let Some(group) = groups.keys().find(|g| result.contains_key(g)) {
+            let msg = format!(
+                "Group '{}' has the same name as a kind.",
+                group.to_str(&string_arena)
+            );
+            return Err(serde::de::Error::custom(msg));
+        }
+        // Warnings of kinds in a group count towards the total of the group
+        let mut limits_kind_totals = LinkedHashMap::new();
+        for (kind, limit) in max_totals {
+            let group = groups.iter().find(|(_group, members)| members.contains(&kind));
+            let limits_kind = match group {
+                Some((group, _members)) => group.clone(),
+                None => kind,
+            };
+            match limits_kind_totals.get(&limits_kind) {
+                Some(other) if *other != limit => {
+                    let msg = format!(
+                        "Kinds in group '{}' declare different `max_total`s.",
+                        limits_kind.to_str(&string_arena)
+                    );
+                    return Err(serde::de::Error::custom(msg));
+                }
+                _ => {
+                    limits_kind_totals.insert(limits_kind, limit);
+                }
+            }
+        }
+        // A warning reported by several kinds in a group is kept under any one of them
+        for (group, members) in &groups {
+            let first = weights.inner.get(&members[0]);
+            if members.iter().any(|member| weights.inner.get(member) != first) {
+                let msg = format!(
+                    "Kinds in group '{}' declare different weights.",
+                    group.to_str(&string_arena)
+                );
+                return Err(serde::de::Error::custom(msg));
+            }
         }
         Ok(Settings {
             string_arena: string_arena,
             inner: result,
+            max_totals: limits_kind_totals,
+            weights: weights,
+            groups: groups,
+            limits_file_name: globals
+                .limits_file
+                .unwrap_or_else(|| DEFAULT_LIMITS_FILE_NAME.to_owned()),
+            central_limits: central_limits,
+            strict: globals.strict,
+            stale_logs: globals.stale_logs,
+            missing_culprits: globals.missing_culprits,
+            log_roots: globals.log_roots,
+            streaming_threshold: globals
+                .streaming_threshold_mb
+                .unwrap_or(DEFAULT_STREAMING_THRESHOLD_MB)
+                .saturating_mul(1024 * 1024),
             kinds_to_ignore: HashSet::new(), // Configured by command line
         })
     }
@@ -148,6 +934,43 @@ impl<'de> Deserialize<'de> for SettingsField {
             #[serde(borrow)]
             regex: Cow<'input, str>,
             files: Vec<String>,
+            max_total: Option<MaxTotal>,
+            severity: Option<SeveritySettings>,
+            category: Option<String>,
+            category_normalize: Option<CategorySettings>,
+            #[serde(default)]
+            category_aliases: HashMap<String, String>,
+            weight: Option<u64>,
+            #[serde(default)]
+            category_weights: HashMap<String, u64>,
+            #[serde(default)]
+            severity_weights: HashMap<String, u64>,
+            identity: Option<Vec<String>>,
+            dedup: Option<bool>,
+            group: Option<String>,
+            #[serde(default)]
+            outside_tree: OutsideTree,
+            outside_tree_limit: Option<u64>,
+            default_limit: Option<RawLimit>,
+            #[serde(default)]
+            required: bool,
+            min_files: Option<usize>,
+            #[serde(default)]
+            complete_marker: Vec<String>,
+            #[serde(default)]
+            preprocess: Preprocessing,
+            #[serde(default)]
+            encoding: Encoding,
+            #[serde(default)]
+            lossy_decoding: bool,
+            max_match_lines: Option<usize>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum RawLimit {
+            Number(u64),
+            Infinite(f64),
         }
 
         let raw = RawSettings::deserialize(deserializer)?;
@@ -156,23 +979,129 @@ impl<'de> Deserialize<'de> for SettingsField {
             .build()
             .map_err(serde::de::Error::custom)?;
 
-        let categorizable = {
-            let captures: HashSet<&str> = as_regex.capture_names().flatten().collect();
-            captures.contains("category")
+        let complete_markers = raw
+            .complete_marker
+            .iter()
+            .map(|marker| RegexBuilder::new(marker).multi_line(true).build())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(serde::de::Error::custom)?;
+
+        if raw.max_match_lines == Some(0) {
+            return Err(serde::de::Error::custom(
+                "`max_match_lines` must be at least 1.",
+            ));
+        }
+
+        let captures: HashSet<&str> = as_regex.capture_names().flatten().collect();
+        let categorizable = captures.contains("category") || raw.category.is_some();
+        let severitizable = captures.contains("severity");
+
+        if raw.severity.is_some() && !severitizable {
+            return Err(serde::de::Error::custom(
+                "Severity settings given, but the regex does not capture `severity`.",
+            ));
+        }
+        let normalizes_category =
+            raw.category_normalize.is_some() || !raw.category_aliases.is_empty();
+        if normalizes_category && !categorizable {
+            return Err(serde::de::Error::custom(
+                "Category normalization given, but the regex does not capture `category`.",
+            ));
+        }
+        if !raw.category_weights.is_empty() && !categorizable {
+            return Err(serde::de::Error::custom(
+                "Category weights given, but the regex does not capture `category`.",
+            ));
+        }
+        if !raw.severity_weights.is_empty() && !severitizable {
+            return Err(serde::de::Error::custom(
+                "Severity weights given, but the regex does not capture `severity`.",
+            ));
+        }
+
+        if raw.group.is_some() && (raw.identity.is_some() || raw.dedup.is_some()) {
+            return Err(serde::de::Error::custom(
+                "Kinds in a `group` are deduplicated by the group, and cannot set `identity` or \
+                 `dedup`.",
+            ));
+        }
+        let dedup = raw.dedup.unwrap_or(true);
+        let identity = match &raw.identity {
+            Some(_) if !dedup => {
+                return Err(serde::de::Error::custom(
+                    "An `identity` is given, but `dedup` is false.",
+                ));
+            }
+            Some(fields) => {
+                let not_captured = fields.iter().find(|f| match f.as_str() {
+                    "category" => !categorizable,
+                    f => Identity::FIELDS.contains(&f) && !captures.contains(f),
+                });
+                if let Some(field) = not_captured {
+                    let msg = format!("Identity field `{}` is not captured by the regex.", field);
+                    return Err(serde::de::Error::custom(msg));
+                }
+                Identity::from_fields(fields.iter().map(String::as_str))
+                    .map_err(serde::de::Error::custom)?
+            }
+            None if raw.group.is_some() => Identity::across_kinds(),
+            None => Identity::default(),
+        };
+
+        let default_limit = match raw.default_limit {
+            None => Some(0),
+            Some(RawLimit::Number(x)) => Some(x),
+            Some(RawLimit::Infinite(f)) if f.is_infinite() && f.is_sign_positive() => None,
+            Some(RawLimit::Infinite(_)) => {
+                return Err(serde::de::Error::custom(
+                    "`default_limit` can only be a positive integer or `inf`.",
+                ))
+            }
         };
+        if raw.outside_tree_limit.is_some() && raw.outside_tree != OutsideTree::Count {
+            return Err(serde::de::Error::custom(
+                "An `outside_tree_limit` is given, but `outside_tree` is not \"count\".",
+            ));
+        }
+
+        let mut category = raw.category_normalize.unwrap_or_default();
+        category.aliases = raw.category_aliases;
+        if let Some(template) = &raw.category {
+            let template =
+                CategoryTemplate::parse(template, &captures).map_err(serde::de::Error::custom)?;
+            category.template = Some(template);
+        }
 
         Ok(SettingsField {
             regex: as_regex,
             files: raw.files,
+            max_total: raw.max_total,
+            severity: raw.severity.unwrap_or_default(),
+            category: category,
+            weight: raw.weight,
+            category_weights: raw.category_weights,
+            severity_weights: raw.severity_weights,
+            identity: identity,
+            dedup: dedup,
+            group: raw.group,
+            outside_tree: raw.outside_tree,
+            outside_tree_limit: raw.outside_tree_limit,
+            default_limit: default_limit,
+            // A required kind needs at least one log file, unless told how many
+            min_files: raw.min_files.unwrap_or(if raw.required { 1 } else { 0 }),
+            complete_markers: complete_markers,
+            preprocessing: raw.preprocess,
+            encoding: raw.encoding,
+            lossy_decoding: raw.lossy_decoding,
+            max_match_lines: raw.max_match_lines.unwrap_or(DEFAULT_MAX_MATCH_LINES),
             categorizable: categorizable,
+            severitizable: severitizable,
         })
     }
 }
 
 #[cfg(test)]
 mod test {
-    use toml;
-
     use super::*;
 
     #[test]
@@ -228,4 +1157,359 @@ mod test {
         let settings = toml::from_str::<Settings>(settings_str).unwrap();
         assert_eq!(settings.iter().count(), 2);
     }
+
+    #[test]
+    fn can_configure_limits_file_name() {
+        let settings_str = r#"
+        [gcc]
+        regex = "warning: (?P<file>.+)"
+        files = ["**/*.txt"]
+        "#;
+        let settings = toml::from_str::<Settings>(settings_str).unwrap();
+        assert_eq!(settings.limits_file_name(), "Limits.toml");
+
+        let settings_str = r#"
+        limits_file = ".wcnt-limits.json"
+
+        [gcc]
+        regex = "warning: (?P<file>.+)"
+        files = ["**/*.txt"]
+        "#;
+        let settings = toml::from_str::<Settings>(settings_str).unwrap();
+        assert_eq!(settings.limits_file_name(), ".wcnt-limits.json");
+        assert_eq!(settings.kinds().count(), 1);
+    }
+
+    #[test]
+    fn limits_can_be_centralized() {
+        let settings_str = r#"
+        [gcc]
+        regex = "warning: (?P<file>.+)"
+        files = ["**/*.txt"]
+
+        [limits."src/component_c"]
+        gcc = 3
+        "#;
+        let settings = toml::from_str::<Settings>(settings_str).unwrap();
+        assert_eq!(settings.central_limits(), Some(&CentralLimitsSource::Settings));
+        assert_eq!(settings.kinds().count(), 1);
+
+        let settings_str = r#"
+        central_limits = "wcnt-limits.toml"
+        "#;
+        let settings = toml::from_str::<Settings>(settings_str).unwrap();
+        assert_eq!(
+            settings.central_limits(),
+            Some(&CentralLimitsSource::File(PathBuf::from("wcnt-limits.toml")))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Limits are given in both the `limits` table and in `central_limits`")]
+    fn limits_are_centralized_in_one_place() {
+        let settings_str = r#"
+        central_limits = "wcnt-limits.toml"
+
+        [limits."src"]
+        gcc = 3
+        "#;
+        toml::from_str::<Settings>(settings_str).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "An `outside_tree_limit` is given, but `outside_tree` is not")]
+    fn outside_tree_limit_requires_counting() {
+        let settings_str = r#"
+        [gcc]
+        regex = "warning: (?P<file>.+)"
+        files = ["**/*.txt"]
+        outside_tree = "ignore"
+        outside_tree_limit = 3
+        "#;
+        toml::from_str::<Settings>(settings_str).unwrap();
+    }
+
+    #[test]
+    fn logs_are_decoded_by_byte_order_mark_or_encoding() {
+        let utf16le: Vec<u8> = vec![0xFF, 0xFE, b'a', 0, b'.', 0, b'c', 0];
+        assert_eq!(Encoding::Utf8.decode(&utf16le, false).unwrap(), "a.c");
+        assert!(!Encoding::Utf8.is_splittable_at_newlines(&utf16le));
+
+        let latin1 = b"gr\xf6\xdfe.c";
+        assert_eq!(Encoding::Latin1.decode(latin1, false).unwrap(), "gr\u{f6}\u{df}e.c");
+        assert!(Encoding::Latin1.is_splittable_at_newlines(latin1));
+        assert!(!Encoding::Utf16Le.is_splittable_at_newlines(latin1));
+        assert!(Encoding::Utf8.decode(latin1, false).is_err());
+        assert_eq!(Encoding::Utf8.decode(latin1, true).unwrap(), "gr\u{fffd}\u{fffd}e.c");
+
+        let utf16be_odd: Vec<u8> = vec![0, b'a', 0];
+        assert!(Encoding::Utf16Be.decode(&utf16be_odd, false).is_err());
+        assert_eq!(Encoding::Utf16Be.decode(&utf16be_odd, true).unwrap(), "a\u{fffd}");
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown field `limit_file`")]
+    fn unknown_options_are_rejected() {
+        let settings_str = r#"
+        limit_file = "Limits.json"
+        "#;
+        toml::from_str::<Settings>(settings_str).unwrap();
+    }
+
+    #[test]
+    fn can_deserialize_max_total() {
+        let settings_str = r#"
+        [gcc]
+        regex = "^(?P<file>[^:]+): warning: (?P<description>.+) \\[(?P<category>.+)\\]"
+        files = ["**/gcc.txt"]
+        max_total = { -Wpedantic = 10, _ = 500 }
+
+        [flake8]
+        regex = "^(?P<file>[^:]+): (?P<description>.+)$"
+        files = ["**/lint.txt"]
+        max_total = 300
+        "#;
+
+        let settings = toml::from_str::<Settings>(settings_str).unwrap();
+        let arena = &settings.string_arena;
+        let gcc_kind = Kind::new(arena.get_id("gcc").unwrap());
+        let flake8_kind = Kind::new(arena.get_id("flake8").unwrap());
+        let cat_pedantic = Category::new(arena.get_id("-Wpedantic").unwrap());
+
+        let max_totals: HashMap<&Kind, &Limit> = settings.max_totals().collect();
+        assert_eq!(
+            max_totals.get(&flake8_kind),
+            Some(&&Limit::Number(Threshold::warnings(Some(300))))
+        );
+        let expected_gcc: LinkedHashMap<Category, Threshold> = vec![
+            (cat_pedantic, Threshold::warnings(Some(10))),
+            (Category::none(), Threshold::warnings(Some(500))),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            max_totals.get(&gcc_kind),
+            Some(&&Limit::PerCategory(expected_gcc))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "per category `max_total` but does not capture `category`")]
+    fn per_category_max_total_must_be_categorizable() {
+        let settings_str = r#"
+        [flake8]
+        regex = "^(?P<file>[^:]+): (?P<description>.+)$"
+        files = ["**/lint.txt"]
+        max_total = { E501 = 10 }
+        "#;
+        toml::from_str::<Settings>(settings_str).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Kinds in group 'c-compilers' declare different `max_total`s.")]
+    fn kinds_in_a_group_share_their_max_total() {
+        let settings_str = r#"
+        [gcc]
+        regex = "^(?P<file>[^:]+): (?P<description>.+)$"
+        files = ["**/gcc.log"]
+        group = "c-compilers"
+        max_total = 10
+
+        [clang]
+        regex = "^(?P<file>[^:]+): (?P<description>.+)$"
+        files = ["**/clang.log"]
+        group = "c-compilers"
+        max_total = 20
+        "#;
+        toml::from_str::<Settings>(settings_str).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Kinds in group 'c-compilers' declare different weights.")]
+    fn kinds_in_a_group_share_their_weights() {
+        let settings_str = r#"
+        [gcc]
+        regex = "^(?P<file>[^:]+): (?P<description>.+)$"
+        files = ["**/gcc.log"]
+        group = "c-compilers"
+        weight = 2
+
+        [clang]
+        regex = "^(?P<file>[^:]+): (?P<description>.+)$"
+        files = ["**/clang.log"]
+        group = "c-compilers"
+        "#;
+        toml::from_str::<Settings>(settings_str).unwrap();
+    }
+
+    #[test]
+    fn severities_can_be_mapped_and_ignored() {
+        let settings_str = r#"
+        [pylint]
+        regex = "^(?P<file>[^:]+): (?P<severity>[A-Z])\\d+ (?P<description>.+)$"
+        files = ["**/pylint.txt"]
+        severity = { map = { E = "error", W = "warning", C = "note" }, ignore = ["note"] }
+        "#;
+
+        let settings = toml::from_str::<Settings>(settings_str).unwrap();
+        let pylint_kind = Kind::new(settings.string_arena.get_id("pylint").unwrap());
+        assert!(settings.severitizables().contains(&pylint_kind));
+
+        let regexes = settings.kinds_and_regex();
+        let severity = &regexes.get(&pylint_kind).unwrap().severity;
+        assert_eq!(severity.normalize("E"), "error");
+        assert_eq!(severity.normalize("F"), "F");
+        assert!(severity.should_ignore(severity.normalize("C")));
+        assert!(!severity.should_ignore(severity.normalize("W")));
+    }
+
+    #[test]
+    #[should_panic(expected = "Category weights given, but the regex does not capture `category`")]
+    fn category_weights_require_capture() {
+        let settings_str = r#"
+        [flake8]
+        regex = "^(?P<file>[^:]+): (?P<description>.+)$"
+        files = ["**/lint.txt"]
+        category_weights = { E501 = 3 }
+        "#;
+        toml::from_str::<Settings>(settings_str).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Identity field `category` is not captured by the regex")]
+    fn identity_fields_must_be_captured() {
+        let settings_str = r#"
+        [flake8]
+        regex = "^(?P<file>[^:]+):(?P<line>\\d+): (?P<description>.+)$"
+        files = ["**/lint.txt"]
+        identity = ["file", "line", "category"]
+        "#;
+        toml::from_str::<Settings>(settings_str).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown identity field `message`")]
+    fn identity_fields_must_be_known() {
+        let settings_str = r#"
+        [flake8]
+        regex = "^(?P<file>[^:]+):(?P<line>\\d+): (?P<description>.+)$"
+        files = ["**/lint.txt"]
+        identity = ["file", "message"]
+        "#;
+        toml::from_str::<Settings>(settings_str).unwrap();
+    }
+
+    #[test]
+    fn categories_can_be_normalized_and_aliased() {
+        let settings_str = r#"
+        [pylint]
+        regex = "^(?P<file>[^:]+): (?P<category>[^ ]+) (?P<description>.+)$"
+        files = ["**/pylint.txt"]
+
+        [pylint.category_normalize]
+        fold_case = true
+        strip_prefixes = ["-W", "["]
+        strip_suffixes = ["]"]
+
+        [pylint.category_aliases]
+        w0611 = "unused-import"
+        "#;
+
+        let settings = toml::from_str::<Settings>(settings_str).unwrap();
+        let pylint_kind = Kind::new(settings.string_arena.get_id("pylint").unwrap());
+        let regexes = settings.kinds_and_regex();
+        let category = &regexes.get(&pylint_kind).unwrap().category;
+        assert_eq!(category.normalize("W0611"), "unused-import");
+        assert_eq!(category.normalize("[Unused-Import]"), "unused-import");
+        assert_eq!(category.normalize("-WPedantic"), "pedantic");
+        assert_eq!(category.normalize("C0301"), "c0301");
+    }
+
+    #[test]
+    #[should_panic(expected = "uses `code`, which is not captured by the regex")]
+    fn category_template_must_use_captures() {
+        let settings_str = r#"
+        [tidy]
+        regex = "^(?P<file>[^:]+): (?P<description>.+) \\[(?P<tool>\\w+): (?P<rule>\\w+)\\]$"
+        files = ["**/tidy.txt"]
+        category = "{tool}:{code}"
+        "#;
+        toml::from_str::<Settings>(settings_str).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Category normalization given, but the regex does not capture")]
+    fn category_normalization_requires_capture() {
+        let settings_str = r#"
+        [flake8]
+        regex = "^(?P<file>[^:]+): (?P<description>.+)$"
+        files = ["**/lint.txt"]
+        category_aliases = { W0611 = "unused-import" }
+        "#;
+        toml::from_str::<Settings>(settings_str).unwrap();
+    }
+
+    #[test]
+    fn kinds_can_be_grouped() {
+        let settings_str = r#"
+        [gcc]
+        regex = "^(?P<file>[^:]+):(?P<line>\\d+): warning: (?P<description>.+) \\[(?P<category>.+)\\]$"
+        files = ["**/gcc.txt"]
+        group = "c-compilers"
+
+        [clang]
+        regex = "^(?P<file>[^:]+):(?P<line>\\d+): warning: (?P<description>.+) \\[(?P<category>.+)\\]$"
+        files = ["**/clang.txt"]
+        group = "c-compilers"
+
+        [flake8]
+        regex = "^(?P<file>[^:]+): (?P<description>.+)$"
+        files = ["**/lint.txt"]
+        "#;
+
+        let mut settings = toml::from_str::<Settings>(settings_str).unwrap();
+        let arena = &settings.string_arena;
+        let gcc = Kind::new(arena.get_id("gcc").unwrap());
+        let flake8 = Kind::new(arena.get_id("flake8").unwrap());
+        let group = Kind::new(arena.get_id("c-compilers").unwrap());
+
+        assert_eq!(settings.group_of(&gcc), Some(&group));
+        assert_eq!(settings.group_of(&flake8), None);
+        assert!(settings.categorizables().contains(&group));
+        let expected: HashSet<&Kind> = vec![&group, &flake8].into_iter().collect();
+        assert_eq!(settings.limit_kinds(), expected);
+
+        // A group is only counted in full when all of its members run
+        settings.configure_kinds_to_run(&Some(vec!["gcc".to_owned(), "flake8".to_owned()]));
+        let expected: HashSet<&Kind> = vec![&flake8].into_iter().collect();
+        assert_eq!(settings.limit_kinds(), expected);
+
+        settings.kinds_to_ignore.clear();
+        settings.configure_kinds_to_run(&Some(vec!["c-compilers".to_owned()]));
+        assert_eq!(settings.kinds().count(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Group 'gcc' has the same name as a kind")]
+    fn group_names_must_not_be_kinds() {
+        let settings_str = r#"
+        [gcc]
+        regex = "^(?P<file>[^:]+): (?P<description>.+)$"
+        files = ["**/gcc.txt"]
+        group = "gcc"
+        "#;
+        toml::from_str::<Settings>(settings_str).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "does not capture `severity`")]
+    fn severity_settings_require_capture() {
+        let settings_str = r#"
+        [gcc]
+        regex = "^(?P<file>[^:]+): warning: (?P<description>.+)$"
+        files = ["**/gcc.txt"]
+        severity = { ignore = ["note"] }
+        "#;
+        toml::from_str::<Settings>(settings_str).unwrap();
+    }
 }
@@ -123,7 +123,7 @@ mod test {
 
     #[test]
     fn fmt_helper_works() {
-        struct Foo { value: usize };
+        struct Foo { value: usize }
         struct AsBinary(bool);
 
         impl Foo {
@@ -1,12 +1,13 @@
 //! Module responsible for structures and functions related to what counts as a warning.
 use std::cmp::Ordering;
 use std::fmt::Display;
+use std::hash::{Hash, Hasher};
 use std::num::NonZeroUsize;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use id_arena::Id;
 
-use crate::limits::{Category, LimitsEntry};
+use crate::limits::{Category, LimitsEntry, Metric, Severity, Threshold};
 use crate::settings::Kind;
 use crate::utils;
 use crate::utils::SearchableArena;
@@ -47,22 +48,182 @@ impl Description {
     }
 }
 
-#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+/// Which parts of a [warning](struct.CountsTowardsLimit.html) are used to tell it apart from other
+/// warnings of the same [Kind](../settings/struct.Kind.html). Warnings that are equal in all these
+/// parts are only counted once. By default, all parts are used. Kinds in a group also tell apart
+/// warnings of the other kinds in the group, so the Kind itself is not always part of the identity.
+pub(crate) struct Identity {
+    kind: bool,
+    file: bool,
+    line: bool,
+    column: bool,
+    severity: bool,
+    category: bool,
+    description: bool,
+}
+
+impl Default for Identity {
+    fn default() -> Self {
+        Identity {
+            kind: true,
+            file: true,
+            line: true,
+            column: true,
+            severity: true,
+            category: true,
+            description: true,
+        }
+    }
+}
+
+impl Identity {
+    /// The names of the parts a warning may be identified by. They are the same as the names of
+    /// the corresponding capture groups.
+    pub(crate) const FIELDS: &'static [&'static str] =
+        &["file", "line", "column", "severity", "category", "description"];
+
+    pub(crate) fn from_fields<'a>(
+        fields: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Self, String> {
+        let mut result = Identity {
+            kind: true,
+            file: false,
+            line: false,
+            column: false,
+            severity: false,
+            category: false,
+            description: false,
+        };
+        for field in fields {
+            match field {
+                "file" => result.file = true,
+                "line" => result.line = true,
+                "column" => result.column = true,
+                "severity" => result.severity = true,
+                "category" => result.category = true,
+                "description" => result.description = true,
+                _ => {
+                    return Err(format!(
+                        "Unknown identity field `{}`, expected one of: {}.",
+                        field,
+                        Identity::FIELDS.join(", ")
+                    ))
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// The identity of warnings of kinds in a group. The same warning is often reported by all
+    /// kinds in the group, but with different columns and descriptions.
+    pub(crate) fn across_kinds() -> Self {
+        Identity {
+            kind: false,
+            file: true,
+            line: true,
+            column: false,
+            severity: false,
+            category: true,
+            description: false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+/// Where a warning was found, as the log file and the offset into it. Only kept for kinds which
+/// should not be deduplicated, making every occurrence of a warning count.
+pub(crate) struct Occurrence {
+    log_file: Id<String>,
+    offset: usize,
+}
+
+impl Occurrence {
+    pub fn new(log_file: Id<String>, offset: usize) -> Self {
+        Occurrence {
+            log_file: log_file,
+            offset: offset,
+        }
+    }
+
+    pub fn remap_id(&mut self, from: &SearchableArena, to: &SearchableArena) {
+        let log_str = from
+            .lookup(self.log_file)
+            .expect("String not present in new arena. Did you forget to call add_all?");
+        self.log_file = to
+            .get_id(log_str)
+            .expect("String not present in new arena. Did you forget to call add_all?");
+    }
+}
+
+#[derive(Clone, Debug)]
 /// A warning is anything that counts towards a limit. We identity it with a path to a `culprit`,
 /// the [Kind](../settings/struct.Kind.html) causing us to look for the warning in the first place,
-/// and optionally line, column, [Category](../limits/struct.Category.html) and [Description](struct.Description.html)
+/// and optionally line, column, [Severity](../limits/struct.Severity.html),
+/// [Category](../limits/struct.Category.html) and [Description](struct.Description.html).
+/// Which of these are compared when deduplicating warnings is decided by its
+/// [Identity](struct.Identity.html).
 pub(crate) struct CountsTowardsLimit {
     culprit: PathBuf,
     line: Option<NonZeroUsize>,
     column: Option<NonZeroUsize>,
     kind: Kind,
+    severity: Severity,
     category: Category,
     description: Description,
+    identity: Identity,
+    occurrence: Option<Occurrence>,
+}
+
+impl PartialEq for CountsTowardsLimit {
+    fn eq(&self, other: &CountsTowardsLimit) -> bool {
+        let id = &self.identity;
+        self.identity == other.identity
+            && (!id.kind || self.kind == other.kind)
+            && self.occurrence == other.occurrence
+            && (!id.file || self.culprit == other.culprit)
+            && (!id.line || self.line == other.line)
+            && (!id.column || self.column == other.column)
+            && (!id.severity || self.severity == other.severity)
+            && (!id.category || self.category == other.category)
+            && (!id.description || self.description == other.description)
+    }
+}
+
+impl Eq for CountsTowardsLimit {}
+
+impl Hash for CountsTowardsLimit {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let id = &self.identity;
+        self.identity.hash(state);
+        self.occurrence.hash(state);
+        if id.kind {
+            self.kind.hash(state);
+        }
+        if id.file {
+            self.culprit.hash(state);
+        }
+        if id.line {
+            self.line.hash(state);
+        }
+        if id.column {
+            self.column.hash(state);
+        }
+        if id.severity {
+            self.severity.hash(state);
+        }
+        if id.category {
+            self.category.hash(state);
+        }
+        if id.description {
+            self.description.hash(state);
+        }
+    }
 }
 
 impl PartialOrd for CountsTowardsLimit {
     fn partial_cmp(&self, other: &CountsTowardsLimit) -> Option<Ordering> {
-        Some(self.cmp(&other))
+        Some(self.cmp(other))
     }
 }
 
@@ -92,14 +253,52 @@ impl CountsTowardsLimit {
             line: line,
             column: column,
             kind: kind,
+            severity: Severity::none(),
             category: category,
             description: desc,
+            identity: Identity::default(),
+            occurrence: None,
         }
     }
 
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    pub fn with_identity(mut self, identity: Identity) -> Self {
+        self.identity = identity;
+        self
+    }
+
+    pub fn with_occurrence(mut self, occurrence: Occurrence) -> Self {
+        self.occurrence = Some(occurrence);
+        self
+    }
+
+    pub fn culprit(&self) -> &Path {
+        self.culprit.as_path()
+    }
+
+    pub fn kind(&self) -> &Kind {
+        &self.kind
+    }
+
+    pub fn category(&self) -> &Category {
+        &self.category
+    }
+
+    pub fn severity(&self) -> &Severity {
+        &self.severity
+    }
+
     pub fn remap(mut self, from: &SearchableArena, to: &SearchableArena) -> Self {
-        self.category.remap_id(&from, &to);
-        self.description.remap_id(&from, &to);
+        self.severity.remap_id(from, to);
+        self.category.remap_id(from, to);
+        self.description.remap_id(from, to);
+        if let Some(occurrence) = self.occurrence.as_mut() {
+            occurrence.remap_id(from, to);
+        }
         self
     }
 
@@ -119,10 +318,13 @@ impl CountsTowardsLimit {
                 fmt_nonzero(self.column),
             )?;
 
-            if let Some(desc_str) = self.description.to_str(&arena) {
+            if let Some(sev_str) = self.severity.to_str(arena) {
+                write!(f, ": {}", sev_str)?;
+            }
+            if let Some(desc_str) = self.description.to_str(arena) {
                 write!(f, ": {}", desc_str)?;
             }
-            if let Some(cat_str) = self.category.to_str(&arena) {
+            if let Some(cat_str) = self.category.to_str(arena) {
                 write!(f, " [{}]", cat_str)?;
             }
             Ok(())
@@ -130,23 +332,33 @@ impl CountsTowardsLimit {
     }
 }
 
-/// A EntryCount is a pairing of a [Limit](../limits/struct.Limit.html) with an actual warning count.
+/// A EntryCount is a pairing of a [Limit](../limits/struct.Limit.html) with an actual count, measured
+/// in the [Metric](../limits/enum.Metric.html) of that limit. If the limit also caps the number of
+/// warnings per file, the culprits exceeding that cap are kept as `hotspots`.
 pub(crate) struct EntryCount<'entry> {
     entry: &'entry LimitsEntry,
     limit: Option<u64>,
+    metric: Metric,
+    max_per_file: Option<u64>,
     pub(crate) actual: u64,
+    hotspots: Vec<(&'entry Path, u64)>,
 }
 
 impl<'entry> EntryCount<'entry> {
     pub fn new(
         limits_entry: &'entry LimitsEntry,
-        threshold: Option<u64>,
-        num_warnings: u64,
+        threshold: Threshold,
+        actual: u64,
+        mut hotspots: Vec<(&'entry Path, u64)>,
     ) -> Self {
+        hotspots.sort();
         EntryCount {
             entry: limits_entry,
-            limit: threshold,
-            actual: num_warnings,
+            limit: threshold.limit,
+            metric: threshold.metric,
+            max_per_file: threshold.max_per_file,
+            actual: actual,
+            hotspots: hotspots,
         }
     }
 
@@ -154,36 +366,63 @@ impl<'entry> EntryCount<'entry> {
         self.entry
     }
 
+    pub fn hotspots(&self) -> &[(&'entry Path, u64)] {
+        &self.hotspots
+    }
+
+    pub fn is_violation(&self) -> bool {
+        let over_limit = match self.limit {
+            Some(limit) => self.actual > limit,
+            None => false,
+        };
+        over_limit || !self.hotspots.is_empty()
+    }
+
     pub fn display<'me, 'arena: 'me>(
         &'me self,
         arena: &'arena SearchableArena,
     ) -> impl Display + 'me {
         utils::fmt_helper(move |f| {
+            let unit = match self.metric {
+                Metric::Warnings => "",
+                Metric::Files => " files",
+                Metric::Score => " points",
+            };
             if let Some(limit) = self.limit {
                 write!(
                     f,
-                    "{} ({} {} {})",
-                    self.entry.display(&arena),
+                    "{} ({}{} {} {})",
+                    self.entry.display(arena),
                     self.actual,
+                    unit,
                     if self.actual > limit { ">" } else { "<=" },
                     limit
-                )
+                )?;
             } else {
-                write!(f, "{} ({} < inf)", self.entry.display(&arena), self.actual,)
+                write!(f, "{} ({}{} < inf)", self.entry.display(arena), self.actual, unit)?;
             }
+            if let Some(max_per_file) = self.max_per_file {
+                write!(
+                    f,
+                    " ({} files > {} per file)",
+                    self.hotspots.len(),
+                    max_per_file
+                )?;
+            }
+            Ok(())
         })
     }
 }
 
 impl<'e> PartialOrd for EntryCount<'e> {
     fn partial_cmp(&self, other: &EntryCount) -> Option<Ordering> {
-        Some(self.cmp(&other))
+        Some(self.cmp(other))
     }
 }
 
 impl<'e> PartialEq for EntryCount<'e> {
     fn eq(&self, other: &EntryCount) -> bool {
-        self.entry.eq(&other.entry) && self.limit.eq(&other.limit) && self.actual.eq(&other.actual)
+        self.entry.eq(other.entry) && self.limit.eq(&other.limit) && self.actual.eq(&other.actual)
     }
 }
 
@@ -191,7 +430,7 @@ impl<'e> Eq for EntryCount<'e> {}
 
 impl<'e> Ord for EntryCount<'e> {
     fn cmp(&self, other: &EntryCount) -> Ordering {
-        match self.entry.cmp(&other.entry) {
+        match self.entry.cmp(other.entry) {
             Ordering::Equal => match self.limit.cmp(&other.limit) {
                 Ordering::Equal => self.actual.cmp(&other.actual),
                 threshold_cmp => threshold_cmp,
@@ -217,7 +456,7 @@ impl<'a> FinalTally<'a> {
     }
 
     pub(crate) fn add(&mut self, entry: EntryCount<'a>) {
-        if entry.limit.is_some() && entry.actual > entry.limit.unwrap() {
+        if entry.is_violation() {
             self.violations.push(entry);
             self.violations.sort();
         } else {